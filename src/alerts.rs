@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::ZfsError;
+use crate::logging;
+use crate::zfs_management::{run_command_with_stdin, ZfsManager};
+
+// How often pool health is polled; override with ZFS_AGENT_WEBHOOK_INTERVAL_SECS
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+// A pool that stays in (or flaps back into) the same bad state is re-alerted at most this often
+const ALERT_COOLDOWN: Duration = Duration::from_secs(3600);
+
+#[derive(Serialize)]
+struct PoolAlert<'a> {
+    pool: &'a str,
+    /// None on the first poll after startup
+    old_health: Option<&'a str>,
+    new_health: &'a str,
+    /// The "status:" explanation from zpool status, if any
+    error: Option<String>,
+    /// Seconds since the epoch
+    timestamp: u64,
+}
+
+// Posts a JSON alert to ZFS_AGENT_WEBHOOK whenever a pool leaves the ONLINE state
+pub struct PoolAlerter {
+    webhook: String,
+    pub interval: Duration,
+    last_health: HashMap<String, String>,
+    last_alert: HashMap<String, (String, Instant)>,
+}
+
+impl PoolAlerter {
+    // None when no webhook is configured, which disables the poller
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(webhook) = std::env::var("ZFS_AGENT_WEBHOOK").ok().filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+        if !(webhook.starts_with("http://") || webhook.starts_with("https://")) || webhook.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(format!("ZFS_AGENT_WEBHOOK '{}' is not an http(s) URL", webhook));
+        }
+        let interval = std::env::var("ZFS_AGENT_WEBHOOK_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_POLL_INTERVAL);
+        Ok(Some(PoolAlerter {
+            webhook,
+            interval,
+            last_health: HashMap::new(),
+            last_alert: HashMap::new(),
+        }))
+    }
+
+    pub fn webhook(&self) -> &str {
+        &self.webhook
+    }
+
+    // One polling round: compare every pool's health with the previous round and alert on changes
+    pub async fn poll(&mut self, zfs: &ZfsManager) {
+        let pools = match zfs.list_pools().await {
+            Ok(pools) => pools,
+            Err(e) => {
+                logging::warn(&format!("Pool health poll failed: {}", e));
+                return;
+            }
+        };
+
+        // Forget pools that were exported or destroyed, so one imported later under the same name
+        // starts fresh instead of being compared against its old health
+        self.last_health.retain(|name, _| pools.iter().any(|pool| &pool.name == name));
+        self.last_alert.retain(|name, _| pools.iter().any(|pool| &pool.name == name));
+
+        for pool in pools {
+            let old_health = self.last_health.insert(pool.name.clone(), pool.health.clone());
+            if pool.health == "ONLINE" || old_health.as_deref() == Some(pool.health.as_str()) {
+                continue;
+            }
+            let recently_alerted = self
+                .last_alert
+                .get(&pool.name)
+                .is_some_and(|(health, at)| *health == pool.health && at.elapsed() < ALERT_COOLDOWN);
+            if recently_alerted {
+                continue;
+            }
+
+            let alert = PoolAlert {
+                pool: &pool.name,
+                old_health: old_health.as_deref(),
+                new_health: &pool.health,
+                error: zfs.get_pool_problem(&pool.name).await.ok().flatten(),
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            };
+            match self.send(&alert).await {
+                Ok(()) => {
+                    logging::info(&format!("Pool '{}' is {}; alert sent", pool.name, pool.health));
+                    self.last_alert.insert(pool.name.clone(), (pool.health.clone(), Instant::now()));
+                }
+                Err(e) => {
+                    logging::warn(&format!("Cannot deliver alert for pool '{}': {}", pool.name, e));
+                    // Forget this round's state so the next poll retries the alert
+                    match old_health {
+                        Some(health) => self.last_health.insert(pool.name.clone(), health),
+                        None => self.last_health.remove(&pool.name),
+                    };
+                }
+            }
+        }
+    }
+
+    // curl handles https; the payload goes in on stdin and the URL after "--" so neither is parsed as an option
+    async fn send(&self, alert: &PoolAlert<'_>) -> Result<(), ZfsError> {
+        let payload = serde_json::to_vec(alert).map_err(|e| ZfsError::Other(e.to_string()))?;
+        run_command_with_stdin(
+            "curl",
+            &[
+                "-sS", "-f", "--max-time", "10",
+                "-X", "POST",
+                "-H", "Content-Type: application/json",
+                "--data-binary", "@-",
+                "--", &self.webhook,
+            ],
+            &payload,
+        ).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::ZfsError;
+use crate::logging;
+use crate::zfs_management::blocking;
+
+// Default location of the audit log; override with ZFS_AGENT_AUDIT_LOG
+const DEFAULT_AUDIT_LOG: &str = "/var/log/zfs-webmanager-agent-audit.jsonl";
+
+// Most entries GET /audit returns in one call
+pub const MAX_AUDIT_ENTRIES: usize = 1000;
+
+// recent() reads the log backwards in blocks of this size, so the cost follows `limit`, not the file size
+const TAIL_CHUNK: u64 = 64 * 1024;
+
+#[derive(Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Seconds since the epoch
+    pub timestamp: u64,
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// success for 2xx responses, error otherwise
+    pub outcome: String,
+}
+
+// Append-only JSON-lines record of every mutating request (anything but GET, HEAD and OPTIONS)
+#[derive(Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    // Serializes appends so concurrent requests cannot interleave partial lines
+    lock: Arc<Mutex<()>>,
+}
+
+impl AuditLog {
+    pub fn from_env() -> Self {
+        let path = std::env::var("ZFS_AGENT_AUDIT_LOG")
+            .ok()
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| DEFAULT_AUDIT_LOG.to_string());
+        AuditLog {
+            path: PathBuf::from(path),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub fn is_mutating(method: &warp::http::Method) -> bool {
+        !matches!(*method, warp::http::Method::GET | warp::http::Method::HEAD | warp::http::Method::OPTIONS)
+    }
+
+    // Best effort: a read-only or missing log location must not fail the operation being recorded
+    pub async fn record(&self, request_id: &str, method: &str, path: &str, status: u16) {
+        let entry = AuditEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            request_id: request_id.to_string(),
+            method: method.to_string(),
+            path: path.to_string(),
+            status,
+            outcome: if (200..300).contains(&status) { "success" } else { "error" }.to_string(),
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else { return };
+        line.push('\n');
+
+        let (path, lock) = (self.path.clone(), self.lock.clone());
+        let written = blocking(move || {
+            let _guard = lock.lock().unwrap();
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(line.as_bytes()))
+        })
+        .await;
+        if let Err(e) = written {
+            logging::warn(&format!("Cannot write audit log {}: {}", self.path.display(), e));
+        }
+    }
+
+    // The newest `limit` entries, newest first; lines that do not parse are skipped
+    pub async fn recent(&self, limit: usize) -> Result<Vec<AuditEntry>, ZfsError> {
+        let path = self.path.clone();
+        match blocking(move || read_tail(&path, limit.min(MAX_AUDIT_ENTRIES))).await {
+            Ok(entries) => Ok(entries),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// Parse whole lines from the end of the file towards the start until `limit` entries are found
+fn read_tail(path: &std::path::Path, limit: usize) -> std::io::Result<Vec<AuditEntry>> {
+    let mut file = File::open(path)?;
+    let mut pos = file.metadata()?.len();
+    // Bytes read so far that start partway through a line, waiting for the block before them
+    let mut partial: Vec<u8> = Vec::new();
+    let mut entries = Vec::new();
+
+    while entries.len() < limit && pos > 0 {
+        let len = TAIL_CHUNK.min(pos);
+        pos -= len;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut block = vec![0; len as usize];
+        file.read_exact(&mut block)?;
+        block.extend_from_slice(&partial);
+
+        // Everything after the first newline is made of complete lines; at the start of the file, all of it is
+        let start = match (pos, block.iter().position(|&b| b == b'\n')) {
+            (0, _) => 0,
+            (_, Some(newline)) => newline + 1,
+            (_, None) => {
+                partial = block;
+                continue;
+            }
+        };
+        let complete = block.split_off(start);
+        partial = block;
+        for line in complete.split(|&b| b == b'\n').rev() {
+            if entries.len() == limit {
+                break;
+            }
+            if let Ok(entry) = serde_json::from_slice(line) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Enough entries to span several blocks, so lines cut at block boundaries are exercised
+    #[tokio::test]
+    async fn recent_reads_newest_entries_from_the_tail() {
+        let path = std::env::temp_dir().join(format!("zfs-agent-audit-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let audit = AuditLog {
+            path: path.clone(),
+            lock: Arc::new(Mutex::new(())),
+        };
+        for i in 0..3000 {
+            audit.record(&format!("req-{}", i), "POST", "/datasets/tank/data", 200).await;
+        }
+
+        let entries = audit.recent(5000).await.unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(entries.len(), MAX_AUDIT_ENTRIES);
+        assert_eq!(entries[0].request_id, "req-2999");
+        assert_eq!(entries[MAX_AUDIT_ENTRIES - 1].request_id, "req-2000");
+        assert!(entries.windows(2).all(|pair| pair[0].timestamp >= pair[1].timestamp));
+    }
+
+    #[tokio::test]
+    async fn recent_on_missing_log_is_empty() {
+        let audit = AuditLog {
+            path: std::env::temp_dir().join("zfs-agent-audit-test-missing.jsonl"),
+            lock: Arc::new(Mutex::new(())),
+        };
+        assert!(audit.recent(10).await.unwrap().is_empty());
+    }
+}
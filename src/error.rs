@@ -0,0 +1,144 @@
+use std::fmt;
+
+// Errors surfaced by ZfsManager, classified so handlers can report a stable machine-readable code.
+// zfs/zpool and libzetta only give us messages, so most variants are derived from the message text.
+#[derive(Debug)]
+pub enum ZfsError {
+    NotFound(String),
+    Busy(String),
+    PermissionDenied(String),
+    InvalidArgument(String),
+    AlreadyExists(String),
+    VersionUnsupported(String),
+    /// A zfs/zpool child stopped making progress and was killed
+    Timeout(String),
+    /// The pool was imported read-only, so nothing on it can be changed
+    ReadOnly(String),
+    Other(String),
+}
+
+impl ZfsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ZfsError::NotFound(_) => "not_found",
+            ZfsError::Busy(_) => "busy",
+            ZfsError::PermissionDenied(_) => "permission_denied",
+            ZfsError::InvalidArgument(_) => "invalid_argument",
+            ZfsError::AlreadyExists(_) => "already_exists",
+            ZfsError::VersionUnsupported(_) => "version_unsupported",
+            ZfsError::Timeout(_) => "timeout",
+            ZfsError::ReadOnly(_) => "read_only",
+            ZfsError::Other(_) => "internal",
+        }
+    }
+
+    // Keep the error class but append context to the message
+    pub fn with_context(self, context: &str) -> Self {
+        let append = |m: String| format!("{} ({})", m, context);
+        match self {
+            ZfsError::NotFound(m) => ZfsError::NotFound(append(m)),
+            ZfsError::Busy(m) => ZfsError::Busy(append(m)),
+            ZfsError::PermissionDenied(m) => ZfsError::PermissionDenied(append(m)),
+            ZfsError::InvalidArgument(m) => ZfsError::InvalidArgument(append(m)),
+            ZfsError::AlreadyExists(m) => ZfsError::AlreadyExists(append(m)),
+            ZfsError::VersionUnsupported(m) => ZfsError::VersionUnsupported(append(m)),
+            ZfsError::Timeout(m) => ZfsError::Timeout(append(m)),
+            ZfsError::ReadOnly(m) => ZfsError::ReadOnly(append(m)),
+            ZfsError::Other(m) => ZfsError::Other(append(m)),
+        }
+    }
+
+    // Classify the stderr of a failed zfs/zpool command (or a libzetta error carrying it). Only the
+    // fixed errno texts zfs prints are matched; the agent's own errors pick their variant directly.
+    pub fn from_stderr(message: String) -> Self {
+        let lower = message.to_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+
+        if has(&["does not exist", "no such pool", "no such file or directory"]) {
+            ZfsError::NotFound(message)
+        } else if has(&["already exists"]) {
+            ZfsError::AlreadyExists(message)
+        } else if has(&["dataset is busy", "pool or dataset is busy", "resource busy"]) {
+            ZfsError::Busy(message)
+        } else if has(&["permission denied", "operation not permitted", "insufficient privileges"]) {
+            ZfsError::PermissionDenied(message)
+        } else if has(&["read-only file system"]) {
+            ZfsError::ReadOnly(message)
+        } else if has(&["operation not supported", "not supported by this system", "pool must be upgraded"]) {
+            ZfsError::VersionUnsupported(message)
+        } else {
+            ZfsError::Other(message)
+        }
+    }
+}
+
+impl fmt::Display for ZfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZfsError::NotFound(m)
+            | ZfsError::Busy(m)
+            | ZfsError::PermissionDenied(m)
+            | ZfsError::InvalidArgument(m)
+            | ZfsError::AlreadyExists(m)
+            | ZfsError::VersionUnsupported(m)
+            | ZfsError::Timeout(m)
+            | ZfsError::ReadOnly(m)
+            | ZfsError::Other(m) => f.write_str(m),
+        }
+    }
+}
+
+impl std::error::Error for ZfsError {}
+
+impl From<std::io::Error> for ZfsError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => ZfsError::NotFound(e.to_string()),
+            std::io::ErrorKind::PermissionDenied => ZfsError::PermissionDenied(e.to_string()),
+            std::io::ErrorKind::AlreadyExists => ZfsError::AlreadyExists(e.to_string()),
+            std::io::ErrorKind::InvalidInput => ZfsError::InvalidArgument(e.to_string()),
+            _ => ZfsError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<libzetta::zfs::Error> for ZfsError {
+    fn from(e: libzetta::zfs::Error) -> Self {
+        ZfsError::from_stderr(e.to_string())
+    }
+}
+
+impl From<libzetta::zpool::ZpoolError> for ZfsError {
+    fn from(e: libzetta::zpool::ZpoolError) -> Self {
+        ZfsError::from_stderr(e.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for ZfsError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        ZfsError::Other(format!("Unexpected numeric output from zfs: {}", e))
+    }
+}
+
+impl From<std::time::SystemTimeError> for ZfsError {
+    fn from(e: std::time::SystemTimeError) -> Self {
+        ZfsError::Other(format!("System clock is before the Unix epoch: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stderr_classification_matches_errno_texts_only() {
+        let classify = |m: &str| ZfsError::from_stderr(m.to_string()).code();
+        assert_eq!(classify("cannot open 'tank/x': dataset does not exist"), "not_found");
+        assert_eq!(classify("cannot destroy 'tank/x': dataset is busy"), "busy");
+        assert_eq!(classify("cannot create 'tank/x@s': Read-only file system"), "read_only");
+        // Words that merely appear in a message no longer pick the class
+        assert_eq!(classify("cannot import 'tank': pool uses the following feature(s) not supported by this system"), "version_unsupported");
+        assert_eq!(classify("cannot attach: device requires a feature flag"), "internal");
+        assert_eq!(classify("pool I/O is currently suspended; you must run zpool clear"), "internal");
+    }
+}
@@ -0,0 +1,47 @@
+use warp::{Rejection, Reply};
+
+use crate::models::*;
+use crate::zfs_management::ZfsManager;
+use super::{error_response, json_response};
+
+// Route handlers for bookmarks
+pub async fn list_bookmarks_handler(
+    dataset: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.list_bookmarks(&dataset).await {
+        Ok(bookmarks) => Ok(json_response(&BookmarkListResponse {
+            bookmarks,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn create_bookmark_handler(
+    dataset: String,
+    body: CreateBookmark,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.create_bookmark(&dataset, &body.snapshot, &body.name).await {
+        Ok(bookmark) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Bookmark {} created", bookmark),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn destroy_bookmark_handler(
+    dataset: String,
+    bookmark: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.destroy_bookmark(&dataset, &bookmark).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: "Bookmark deleted successfully".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
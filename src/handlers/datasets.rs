@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use futures_util::{Stream, TryStreamExt};
+use tokio_util::io::StreamReader;
+use warp::{Buf, Rejection, Reply};
+use zeroize::Zeroize;
+
+use crate::error::ZfsError;
+use crate::models::*;
+use crate::zfs_management::ZfsManager;
+use super::{error_body, error_response, exists_status, json_response, list_sort, paginate};
+
+// Route handlers for datasets
+pub async fn list_datasets_handler(
+    pool: String,
+    query: DatasetListQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match list_datasets_sorted(&pool, &query, &zfs).await {
+        Ok(datasets) => {
+            let (datasets, total, has_more) = paginate(datasets, query.offset, query.limit);
+            Ok(json_response(&DatasetResponse {
+                datasets,
+                total,
+                has_more,
+                status: "success".to_string(),
+            }))
+        }
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+async fn list_datasets_sorted(pool: &str, query: &DatasetListQuery, zfs: &ZfsManager) -> Result<Vec<String>, ZfsError> {
+    let (by_creation, descending) = list_sort(query.sort.as_deref(), query.order.as_deref())?;
+    let mut datasets = if by_creation {
+        let mut created = zfs.list_datasets_with_creation(pool).await?;
+        created.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        created.into_iter().map(|(name, _)| name).collect()
+    } else {
+        let mut datasets = zfs.list_datasets(pool).await?;
+        datasets.sort();
+        datasets
+    };
+    if descending {
+        datasets.reverse();
+    }
+    Ok(datasets)
+}
+
+pub async fn batch_properties_handler(
+    body: BatchPropertiesRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_properties_batch(&body.datasets, &body.fields).await {
+        Ok(datasets) => Ok(json_response(&BatchPropertiesResponse {
+            datasets,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn dataset_by_mountpoint_handler(
+    query: MountpointQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.find_dataset_by_mountpoint(&query.path).await {
+        Ok(Some((dataset, mountpoint))) => Ok(json_response(&MountpointLookupResponse {
+            dataset,
+            mountpoint,
+            status: "success".to_string(),
+        })),
+        Ok(None) => Ok(error_response(&ZfsError::NotFound(format!(
+            "No mounted dataset provides path '{}'",
+            query.path
+        )))),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn create_dataset_handler(
+    body: CreateDataset,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.create_dataset(body).await {
+        Ok(created) if created.len() > 1 => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Dataset created successfully along with parents: {}", created[..created.len() - 1].join(", ")),
+        })),
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: "Dataset created successfully".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn snapshot_delete_preview_handler(
+    dataset: String,
+    body: SnapshotDeletePreviewRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.preview_snapshot_deletion(&dataset, &body.snapshots).await {
+        Ok((snapshots, reclaimable_bytes)) => Ok(json_response(&SnapshotDeletePreviewResponse {
+            dataset,
+            snapshots,
+            reclaimable_bytes,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn set_dataset_property_handler(
+    dataset: String,
+    body: SetDatasetPropertiesRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let props = match body {
+        SetDatasetPropertiesRequest::Single(single) => HashMap::from([(single.property, single.value)]),
+        SetDatasetPropertiesRequest::Multiple { properties } => properties,
+    };
+    let mut requested: Vec<String> = props.keys().cloned().collect();
+    requested.sort();
+
+    match zfs.set_properties(&dataset, props.clone()).await {
+        Ok(_) => Ok(json_response(&SetPropertiesResponse {
+            message: format!("Set {} on '{}'", requested.join(", "), dataset),
+            dataset,
+            applied: requested,
+            failed: Vec::new(),
+            status: "success".to_string(),
+        })),
+        // Validation failures happen before zfs runs, so nothing can have been applied
+        Err(e @ ZfsError::InvalidArgument(_)) => Ok(error_response(&e)),
+        Err(e) => {
+            let applied = zfs.applied_properties(&dataset, &props).await;
+            if applied.is_empty() {
+                return Ok(error_response(&e));
+            }
+            let failed = requested.into_iter().filter(|p| !applied.contains(p)).collect();
+            Ok(json_response(&SetPropertiesResponse {
+                dataset,
+                applied,
+                failed,
+                message: e.to_string(),
+                status: "partial".to_string(),
+            }))
+        }
+    }
+}
+
+pub async fn get_property_handler(
+    dataset: String,
+    property: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_property(&dataset, &property).await {
+        Ok((value, source, inherited_from)) => Ok(json_response(&PropertyValueResponse {
+            dataset,
+            property,
+            value,
+            source,
+            inherited_from,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn inherit_property_handler(
+    dataset: String,
+    property: String,
+    query: InheritQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.inherit_property(&dataset, &property, query.recursive).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Property '{}' on '{}' now inherits its value", property, dataset),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn set_quota_handler(
+    dataset: String,
+    body: QuotaRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.set_space_limits(&dataset, &body).await {
+        Ok(applied) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Set {} on '{}'", applied.join(", "), dataset),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn snapdir_handler(
+    dataset: String,
+    body: SnapdirRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.set_snapdir(&dataset, body.visible).await {
+        Ok(snapdir) => Ok(json_response(&SnapdirResponse {
+            dataset,
+            snapdir,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn share_dataset_handler(
+    dataset: String,
+    body: ShareRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.set_share(&dataset, &body.protocol, body.options.as_deref()).await {
+        Ok((sharenfs, sharesmb)) => Ok(json_response(&ShareResponse {
+            dataset,
+            sharenfs,
+            sharesmb,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn unshare_dataset_handler(
+    dataset: String,
+    protocol: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.unshare(&dataset, &protocol).await {
+        Ok((sharenfs, sharesmb)) => Ok(json_response(&ShareResponse {
+            dataset,
+            sharenfs,
+            sharesmb,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn rename_dataset_handler(
+    dataset: String,
+    body: RenameDatasetRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.rename_dataset(&dataset, &body.target, body.force_unmount).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Dataset '{}' renamed to '{}'", dataset, body.target),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn mount_dataset_handler(
+    dataset: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.mount_dataset(&dataset).await {
+        Ok(message) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message,
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn unmount_dataset_handler(
+    dataset: String,
+    body: UnmountRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.unmount_dataset(&dataset, body.force).await {
+        Ok(message) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message,
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn load_key_handler(
+    dataset: String,
+    mut body: LoadKeyRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let result = zfs.load_key(&dataset, body.key.as_deref(), body.recursive).await;
+    body.key.zeroize();
+    match result {
+        Ok(keystatus) => Ok(json_response(&KeyStatusResponse {
+            message: format!("Key for '{}' is {}", dataset, keystatus),
+            dataset,
+            keystatus,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn change_key_handler(
+    dataset: String,
+    mut body: ChangeKeyRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let result = change_key(&zfs, &dataset, &body).await;
+    body.current_key.zeroize();
+    body.new_key.zeroize();
+    match result {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Key changed for encryption root '{}'", dataset),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+// Load the current key first when one is given, since zfs change-key needs it loaded
+async fn change_key(zfs: &ZfsManager, dataset: &str, body: &ChangeKeyRequest) -> Result<(), ZfsError> {
+    if let Some(current_key) = body.current_key.as_deref() {
+        zfs.load_key(dataset, Some(current_key), false).await?;
+    }
+    zfs.change_key(
+        dataset,
+        body.new_key.as_deref(),
+        body.new_keyformat.as_deref(),
+        body.new_keylocation.as_deref(),
+    )
+    .await
+}
+
+pub async fn unload_key_handler(
+    dataset: String,
+    body: UnloadKeyRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.unload_key(&dataset, body.recursive).await {
+        Ok(keystatus) => Ok(json_response(&KeyStatusResponse {
+            message: format!("Key for '{}' is {}", dataset, keystatus),
+            dataset,
+            keystatus,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn receive_snapshot_handler(
+    target: String,
+    body: ReceiveSnapshot,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.receive_snapshot_from_file(&target, &body).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Stream received into {}", target),
+        })),
+        Err(e) if body.resumable => Ok(receive_error_response(&zfs, &target, &e).await),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+// A failed resumable receive reports the token the sender needs to continue where it stopped
+async fn receive_error_response(zfs: &ZfsManager, target: &str, e: &ZfsError) -> warp::reply::Response {
+    error_body(e, zfs.receive_resume_token(target).await)
+}
+
+pub async fn receive_stream_handler(
+    target: String,
+    query: ReceiveStreamQuery,
+    body: impl Stream<Item = Result<impl Buf, warp::Error>> + Unpin,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let reader = StreamReader::new(
+        body.map_ok(|mut chunk| chunk.copy_to_bytes(chunk.remaining()))
+            .map_err(std::io::Error::other),
+    );
+    match zfs.receive_snapshot_from_stream(&target, &query, reader).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Stream received into {}", target),
+        })),
+        Err(e) if query.resumable => Ok(receive_error_response(&zfs, &target, &e).await),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn delete_dataset_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.delete_dataset(&name).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: "Dataset deleted successfully".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn dataset_exists_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let status = exists_status(zfs.dataset_exists(&name).await);
+    Ok(warp::reply::with_status(warp::reply(), status))
+}
+
+pub async fn get_dataset_exists_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.dataset_exists(&name).await {
+        Ok(exists) => Ok(json_response(&ExistsResponse {
+            name,
+            exists,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn get_origin_handler(
+    dataset: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_origin(&dataset).await {
+        Ok(origin) => Ok(json_response(&OriginResponse {
+            dataset,
+            origin,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn space_usage_handler(
+    dataset: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_space_usage(&dataset).await {
+        Ok(space) => Ok(json_response(&SpaceUsageResponse {
+            dataset,
+            space,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn prune_snapshots_handler(
+    dataset: String,
+    body: PruneSnapshotsRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let results = if body.dry_run {
+        zfs.select_snapshots_to_prune(&dataset, &body).await.map(|selected| {
+            selected
+                .into_iter()
+                .map(|snapshot| BatchSnapshotResult {
+                    snapshot,
+                    status: "would_delete".to_string(),
+                    message: None,
+                })
+                .collect()
+        })
+    } else {
+        zfs.prune_snapshots(&dataset, &body).await
+    };
+
+    match results {
+        Ok(results) => Ok(json_response(&PruneSnapshotsResponse {
+            dataset,
+            dry_run: body.dry_run,
+            results,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn dataset_tree_handler(
+    root: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.list_dataset_tree(&root).await {
+        Ok(tree) => Ok(json_response(&DatasetTreeResponse {
+            tree,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
@@ -0,0 +1,21 @@
+use warp::{Rejection, Reply};
+
+use crate::models::*;
+use crate::zfs_management::ZfsManager;
+use super::{device_from_tail, error_response, json_response};
+
+// Route handlers for disks
+pub async fn labelclear_handler(
+    device: String,
+    body: LabelClearRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let device = device_from_tail(&device);
+    match zfs.labelclear_device(&device, body.force).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Labels cleared on {}", device),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
@@ -0,0 +1,93 @@
+use serde::Serialize;
+use warp::http::StatusCode;
+use warp::Reply;
+
+use crate::error::ZfsError;
+use crate::models::ErrorResponse;
+
+pub mod bookmarks;
+pub mod datasets;
+pub mod disks;
+pub mod pools;
+pub mod snapshots;
+pub mod system;
+pub mod volumes;
+
+// HTTP status for each error class, so clients can branch without parsing the body
+pub fn error_status(e: &ZfsError) -> StatusCode {
+    match e {
+        ZfsError::NotFound(_) => StatusCode::NOT_FOUND,
+        ZfsError::Busy(_) | ZfsError::AlreadyExists(_) | ZfsError::ReadOnly(_) => StatusCode::CONFLICT,
+        ZfsError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+        ZfsError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+        ZfsError::VersionUnsupported(_) => StatusCode::NOT_IMPLEMENTED,
+        ZfsError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        ZfsError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// HEAD responses carry no body, so the outcome is reported through the status code alone
+pub fn exists_status(result: Result<bool, ZfsError>) -> StatusCode {
+    match result {
+        Ok(true) => StatusCode::OK,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => error_status(&e),
+    }
+}
+
+// Successful JSON body; a Response so handlers can return it alongside error_response
+pub fn json_response<T: Serialize>(body: &T) -> warp::reply::Response {
+    warp::reply::json(body).into_response()
+}
+
+// Error body shared by all handlers; `code` is stable so clients need not match on messages
+pub fn error_response(e: &ZfsError) -> warp::reply::Response {
+    error_body(e, None)
+}
+
+// Error body with the HTTP status for the error class and an optional receive resume token
+pub fn error_body(e: &ZfsError, resume_token: Option<String>) -> warp::reply::Response {
+    let body = warp::reply::json(&ErrorResponse {
+        status: "error".to_string(),
+        message: e.to_string(),
+        code: e.code().to_string(),
+        resume_token,
+    });
+    warp::reply::with_status(body, error_status(e)).into_response()
+}
+
+// Listings are paginated so pools with thousands of snapshots do not produce huge responses
+pub const DEFAULT_LIST_LIMIT: usize = 500;
+
+// Parse the shared sort/order list parameters into (sort by creation, descending)
+pub fn list_sort(sort: Option<&str>, order: Option<&str>) -> Result<(bool, bool), ZfsError> {
+    let by_creation = match sort {
+        None | Some("name") => false,
+        Some("creation") => true,
+        Some(other) => return Err(ZfsError::InvalidArgument(format!("Invalid sort '{}': must be name or creation", other))),
+    };
+    let descending = match order {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => return Err(ZfsError::InvalidArgument(format!("Invalid order '{}': must be asc or desc", other))),
+    };
+    Ok((by_creation, descending))
+}
+
+// Cut one page out of a sorted listing; returns the page, the full count and whether more follow
+pub fn paginate<T>(items: Vec<T>, offset: usize, limit: Option<usize>) -> (Vec<T>, usize, bool) {
+    let total = items.len();
+    let limit = limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    let page: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+    let has_more = offset.saturating_add(page.len()) < total;
+    (page, total, has_more)
+}
+
+// URL tails lose their leading slash, so restore it for absolute device paths like dev/sdb
+pub fn device_from_tail(tail: &str) -> String {
+    if tail.contains('/') {
+        format!("/{}", tail)
+    } else {
+        tail.to_string()
+    }
+}
@@ -0,0 +1,465 @@
+use warp::{Rejection, Reply};
+
+use crate::models::*;
+use crate::zfs_management::ZfsManager;
+use super::{device_from_tail, error_response, exists_status, json_response};
+
+// Route handlers for pools
+pub async fn list_importable_pools_handler(
+    query: ImportableQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let refresh = query.refresh.unwrap_or(false);
+    match zfs.list_importable_pools(query.dir.as_deref(), refresh).await {
+        Ok((pools, scanned_at, cached)) => Ok(json_response(&ImportablePoolsResponse {
+            pools,
+            scanned_at,
+            cached,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn import_pool_handler(
+    body: ImportPoolRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.import_pool(&body).await {
+        Ok(name) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: if body.readonly {
+                format!("Pool '{}' imported read-only; datasets were not mounted", name)
+            } else {
+                format!("Pool '{}' imported", name)
+            },
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn pool_exists_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let status = exists_status(zfs.pool_exists(&name).await);
+    Ok(warp::reply::with_status(warp::reply(), status))
+}
+
+// JSON counterpart of the HEAD check for clients that cannot easily read bare status codes
+pub async fn get_pool_exists_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.pool_exists(&name).await {
+        Ok(exists) => Ok(json_response(&ExistsResponse {
+            name,
+            exists,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn pool_status_handler(
+    name: String,
+    query: PoolStatusQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_pool_status(&name, query.fresh.unwrap_or(false)).await {
+        Ok(((health, vdevs, topology, has_checkpoint), cached, age_ms)) => Ok(json_response(&PoolStatusResponse {
+            name,
+            health,
+            vdevs,
+            topology: Some(topology),
+            has_checkpoint,
+            cached,
+            age_ms,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn scrub_status_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_scan_status(&name).await {
+        Ok(scan) => Ok(json_response(&ScrubStatusResponse {
+            pool: name,
+            is_resilvering: scan.function == "resilver" && scan.state == "scanning",
+            function: scan.function,
+            state: scan.state,
+            percent_done: scan.percent_done,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn resilver_status_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_scan_status(&name).await {
+        Ok(scan) if scan.function == "resilver" => Ok(json_response(&ResilverStatusResponse {
+            pool: name,
+            state: scan.state,
+            percent_done: scan.percent_done,
+            status: "success".to_string(),
+        })),
+        Ok(_) => Ok(json_response(&ResilverStatusResponse {
+            pool: name,
+            state: "none".to_string(),
+            percent_done: None,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn pool_history_handler(
+    name: String,
+    query: PoolHistoryQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_pool_history(&name, query.internal.unwrap_or(false)).await {
+        Ok(entries) => Ok(json_response(&PoolHistoryResponse {
+            pool: name,
+            entries,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn get_pool_properties_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_pool_properties(&name).await {
+        Ok(properties) => Ok(json_response(&PoolPropertiesResponse {
+            pool: name,
+            properties,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn set_pool_property_handler(
+    name: String,
+    body: SetPropertyRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.set_pool_property(&name, &body.property, &body.value).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Property '{}' set on pool '{}'", body.property, name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn replace_vdev_handler(
+    name: String,
+    body: ReplaceVdevRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.replace_vdev(&name, &body.old_device, &body.new_device).await {
+        Ok(resilver_started) => Ok(json_response(&ReplaceVdevResponse {
+            pool: name,
+            old_device: body.old_device,
+            new_device: body.new_device,
+            resilver_started,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn attach_vdev_handler(
+    name: String,
+    body: AttachVdevRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.attach_vdev(&name, &body.existing_device, &body.new_device).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!(
+                "Device '{}' attached to '{}'; resilvering has started",
+                body.new_device, body.existing_device
+            ),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn detach_vdev_handler(
+    name: String,
+    body: DetachVdevRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.detach_vdev(&name, &body.device).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Device '{}' detached from pool '{}'", body.device, name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn split_pool_handler(
+    name: String,
+    body: SplitPoolRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.split_pool(&name, &body.new_pool, body.devices.as_deref()).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Pool '{}' split into '{}'; import '{}' to use it", name, body.new_pool, body.new_pool),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn clear_pool_handler(
+    name: String,
+    body: ClearPoolRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.clear_pool(&name, body.device.as_deref()).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: match &body.device {
+                Some(device) => format!("Errors cleared on device '{}' in pool '{}'", device, name),
+                None => format!("Errors cleared on pool '{}'", name),
+            },
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn offline_vdev_handler(
+    name: String,
+    device: String,
+    body: OfflineVdevRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let device = device_from_tail(&device);
+    match zfs.offline_vdev(&name, &device, body.temporary).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Device '{}' in pool '{}' is offline", device, name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn online_vdev_handler(
+    name: String,
+    device: String,
+    body: OnlineVdevRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let device = device_from_tail(&device);
+    match zfs.online_vdev(&name, &device, body.expand).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Device '{}' in pool '{}' is online", device, name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn start_trim_handler(
+    name: String,
+    body: StartTrimRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.start_trim(&name, body.rate, body.secure).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("TRIM started on pool '{}'", name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn cancel_trim_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.cancel_trim(&name).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("TRIM cancelled on pool '{}'", name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn trim_status_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.trim_status(&name).await {
+        Ok((vdevs, state, percent_done)) => Ok(json_response(&VdevProgressResponse {
+            pool: name,
+            state,
+            percent_done,
+            vdevs,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn start_initialize_handler(
+    name: String,
+    body: InitializeRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.start_initialize(&name, body.device.as_deref()).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Initialize started on pool '{}'", name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn cancel_initialize_handler(
+    name: String,
+    body: InitializeRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.cancel_initialize(&name, body.device.as_deref()).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Initialize cancelled on pool '{}'", name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn initialize_status_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.initialize_status(&name).await {
+        Ok((vdevs, state, percent_done)) => Ok(json_response(&VdevProgressResponse {
+            pool: name,
+            state,
+            percent_done,
+            vdevs,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn pool_iostat_handler(
+    name: String,
+    query: IostatQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_pool_iostat(&name, query.interval_ms).await {
+        Ok((total, vdevs)) => Ok(json_response(&PoolIostatResponse {
+            pool: name,
+            interval_ms: query.interval_ms,
+            total,
+            vdevs,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn checkpoint_pool_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.checkpoint_pool(&name).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Checkpoint created for pool '{}'", name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn discard_checkpoint_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.discard_checkpoint(&name).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Checkpoint of pool '{}' discarded", name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn pool_features_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.get_pool_features(&name).await {
+        Ok(features) => Ok(json_response(&PoolFeaturesResponse {
+            pool: name,
+            upgrade_available: features.values().any(|state| state == "disabled"),
+            features,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn upgrade_pool_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.upgrade_pool(&name).await {
+        Ok(newly_enabled) => Ok(json_response(&PoolUpgradeResponse {
+            pool: name,
+            newly_enabled,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn expand_pool_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.expand_pool(&name).await {
+        Ok((old_size, new_size, devices)) => Ok(json_response(&PoolExpandResponse {
+            pool: name,
+            old_size,
+            new_size,
+            expanded_by: new_size.saturating_sub(old_size),
+            devices,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn reguid_pool_handler(
+    name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.reguid_pool(&name).await {
+        Ok((old_guid, new_guid)) => Ok(json_response(&ReguidResponse {
+            pool: name,
+            old_guid,
+            new_guid,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
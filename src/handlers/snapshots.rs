@@ -0,0 +1,410 @@
+use std::time::{Duration, Instant};
+
+use tokio::io::AsyncReadExt;
+use warp::hyper::body::{Body, Bytes};
+use warp::{Rejection, Reply};
+
+use crate::error::ZfsError;
+use crate::models::*;
+use crate::zfs_management::{throttle_delay, ZfsManager};
+use super::{error_response, json_response, list_sort, paginate};
+
+// Route handlers
+pub async fn list_snapshots_handler(
+    dataset: String,
+    query: SnapshotListQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match list_snapshots_page(&dataset, &query, &zfs).await {
+        Ok(reply) => Ok(reply),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+// Sorting by creation needs the detailed listing even when only names are returned
+async fn list_snapshots_page(dataset: &str, query: &SnapshotListQuery, zfs: &ZfsManager) -> Result<warp::reply::Response, ZfsError> {
+    let (by_creation, descending) = list_sort(query.sort.as_deref(), query.order.as_deref())?;
+
+    if !query.detailed && !by_creation {
+        let mut snapshots = zfs.list_snapshots(dataset).await?;
+        snapshots.sort();
+        if descending {
+            snapshots.reverse();
+        }
+        let (snapshots, total, has_more) = paginate(snapshots, query.offset, query.limit);
+        return Ok(json_response(&ListResponse {
+            snapshots,
+            total,
+            has_more,
+            status: "success".to_string(),
+        }));
+    }
+
+    let mut snapshots = zfs.list_snapshots_detailed(dataset, query.recursive).await?;
+    if by_creation {
+        snapshots.sort_by(|a, b| a.creation.cmp(&b.creation).then_with(|| a.name.cmp(&b.name)));
+    } else {
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    if descending {
+        snapshots.reverse();
+    }
+    let (snapshots, total, has_more) = paginate(snapshots, query.offset, query.limit);
+    if query.detailed {
+        Ok(json_response(&DetailedListResponse {
+            snapshots,
+            total,
+            has_more,
+            status: "success".to_string(),
+        }))
+    } else {
+        Ok(json_response(&ListResponse {
+            snapshots: snapshots.into_iter().map(|snapshot| snapshot.name).collect(),
+            total,
+            has_more,
+            status: "success".to_string(),
+        }))
+    }
+}
+
+pub async fn search_snapshots_handler(
+    query: SnapshotSearchQuery,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.find_snapshots(query.pool.as_deref(), query.contains.as_deref(), query.older_than).await {
+        Ok(snapshots) => Ok(json_response(&SnapshotSearchResponse {
+            snapshots,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn create_snapshot_handler(
+    dataset: String,
+    body: CreateSnapshot,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.create_snapshot(&dataset, &body.snapshot_name, body.recursive).await {
+        Ok(snapshots) => Ok(json_response(&CreateSnapshotResponse {
+            status: "success".to_string(),
+            message: "Snapshot created successfully".to_string(),
+            snapshots,
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn delete_snapshot_handler(
+    dataset: String,
+    snapshot_name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.delete_snapshot(&dataset, &snapshot_name).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: "Snapshot deleted successfully".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn rename_snapshot_handler(
+    dataset: String,
+    snapshot_name: String,
+    body: RenameSnapshot,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.rename_snapshot(&dataset, &snapshot_name, &body.new_name).await {
+        Ok(snapshot) => Ok(json_response(&RenameSnapshotResponse {
+            snapshot,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn hold_snapshot_handler(
+    dataset: String,
+    snapshot_name: String,
+    body: HoldRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.hold_snapshot(&dataset, &snapshot_name, &body.tag).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Hold '{}' placed on {}@{}", body.tag, dataset, snapshot_name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn release_hold_handler(
+    dataset: String,
+    snapshot_name: String,
+    tag: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.release_hold(&dataset, &snapshot_name, &tag).await {
+        Ok(_) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Hold '{}' released from {}@{}", tag, dataset, snapshot_name),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn list_clones_handler(
+    dataset: String,
+    snapshot_name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.list_clones(&dataset, &snapshot_name).await {
+        Ok((snapshot, clones)) => Ok(json_response(&ClonesResponse {
+            snapshot,
+            clones,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn snapshot_deltas_handler(
+    dataset: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.snapshot_deltas(&dataset).await {
+        Ok(snapshots) => Ok(json_response(&SnapshotDeltasResponse {
+            dataset,
+            snapshots,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn list_holds_handler(
+    dataset: String,
+    snapshot_name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.list_holds(&dataset, &snapshot_name).await {
+        Ok((snapshot, holds)) => Ok(json_response(&HoldsResponse {
+            snapshot,
+            holds,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn diff_snapshot_handler(
+    dataset: String,
+    snapshot_name: String,
+    query: DiffQuery,
+    zfs: ZfsManager,
+) -> Result<warp::reply::Response, Rejection> {
+    let from = format!("{}@{}", dataset, snapshot_name);
+    // A bare name in `to` is a snapshot of the same dataset
+    let to = query.to.map(|to| if to.contains('@') { to } else { format!("{}@{}", dataset, to) });
+    let mut diff = match zfs.diff_snapshots(&from, to.as_deref()).await {
+        Ok(diff) => diff,
+        Err(e) => return Ok(error_response(&e)),
+    };
+    // Wait for the first entry, so a diff that fails up front (an unmounted dataset, a missing
+    // snapshot) still gets a proper error status
+    let first = match diff.next_entry().await {
+        Ok(first) => first,
+        Err(e) => return Ok(error_response(&e)),
+    };
+
+    // One JSON object per line (NDJSON), written as zfs produces them
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut next = first;
+        while let Some(entry) = next {
+            let Ok(mut line) = serde_json::to_vec(&entry) else { return };
+            line.push(b'\n');
+            // The client went away; dropping the diff stops zfs
+            if sender.send_data(Bytes::from(line)).await.is_err() {
+                return;
+            }
+            next = match diff.next_entry().await {
+                Ok(next) => next,
+                // Headers are already out, so a failure can only be reported by cutting the body short
+                Err(_) => {
+                    sender.abort();
+                    return;
+                }
+            };
+        }
+    });
+
+    let mut response = warp::reply::Response::new(body);
+    response.headers_mut().insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("application/x-ndjson"),
+    );
+    Ok(response)
+}
+
+pub async fn batch_snapshot_handler(
+    body: BatchSnapshotRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let results = zfs.create_snapshots_batch(&body.snapshots).await;
+    let all_succeeded = results.iter().all(|r| r.status == "success");
+    Ok(json_response(&BatchSnapshotResponse {
+        results,
+        status: if all_succeeded { "success" } else { "error" }.to_string(),
+    }))
+}
+
+pub async fn send_snapshot_handler(
+    dataset: String,
+    snapshot_name: String,
+    body: SendSnapshot,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    let result: Result<(String, (u64, Duration)), ZfsError> = match (&body.output_file, &body.remote_file) {
+        (Some(output_file), None) => zfs
+            .send_snapshot_to_file(&dataset, &snapshot_name, &body, output_file)
+            .await
+            .map(|sent| (output_file.clone(), sent)),
+        (None, Some(target)) => zfs
+            .send_snapshot_to_remote_file(&dataset, &snapshot_name, &body, target)
+            .await
+            .map(|sent| (format!("{}:{}", target.remote_host, target.remote_path), sent)),
+        _ => Err(ZfsError::InvalidArgument("Specify exactly one of 'output_file' or 'remote_file'".to_string())),
+    };
+
+    // Listed after the send so the report matches what the stream carried
+    let datasets = match (&result, body.recursive) {
+        (Ok(_), true) => zfs.recursive_send_members(&dataset, &snapshot_name).await.unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    match result {
+        Ok((destination, (bytes_sent, elapsed))) => Ok(json_response(&SendSnapshotResponse {
+            message: format!("Snapshot sent to {}", destination),
+            bytes_sent,
+            average_bytes_per_sec: (bytes_sent as f64 / elapsed.as_secs_f64().max(0.001)) as u64,
+            datasets,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+// Stream `zfs send` output as the response body so clients can pipe it into their own zfs receive
+pub async fn send_stream_handler(
+    dataset: String,
+    snapshot_name: String,
+    query: SendStreamQuery,
+    zfs: ZfsManager,
+) -> Result<warp::reply::Response, Rejection> {
+    let request = SendSnapshot {
+        output_file: None,
+        remote_file: None,
+        from_snapshot: query.from,
+        include_intermediate: query.include_intermediate,
+        properties: query.properties,
+        embed_data: query.embed_data,
+        large_blocks: query.large_blocks,
+        redact_bookmark: None,
+        raw: query.raw,
+        compressed: query.compressed,
+        recursive: query.recursive,
+        resume_token: query.resume_token,
+        rate_limit_bytes_per_sec: query.rate_limit_bytes_per_sec,
+    };
+    let mut child = match zfs.spawn_send_stream(&dataset, &snapshot_name, &request).await {
+        Ok(child) => child,
+        Err(e) => {
+            return Ok(error_response(&e))
+        }
+    };
+
+    let mut stdout = child.stdout.take().expect("zfs send stdout is piped");
+    let rate_limit = request.rate_limit_bytes_per_sec;
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let mut sent = 0u64;
+        let mut buf = vec![0u8; 128 * 1024];
+        loop {
+            match stdout.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    // The client went away; dropping the child stops zfs send
+                    if sender.send_data(Bytes::copy_from_slice(&buf[..n])).await.is_err() {
+                        return;
+                    }
+                    sent += n as u64;
+                    if let Some(delay) = throttle_delay(sent, started.elapsed(), rate_limit) {
+                        tokio::time::sleep(delay).await;
+                    }
+                }
+                Err(_) => {
+                    sender.abort();
+                    return;
+                }
+            }
+        }
+        // Headers are already out, so a failed send can only be reported by cutting the body short
+        if !matches!(child.wait().await, Ok(status) if status.success()) {
+            sender.abort();
+        }
+    });
+
+    let mut response = warp::reply::Response::new(body);
+    response.headers_mut().insert(
+        warp::http::header::CONTENT_TYPE,
+        warp::http::HeaderValue::from_static("application/octet-stream"),
+    );
+    Ok(response)
+}
+
+pub async fn create_redaction_bookmark_handler(
+    dataset: String,
+    snapshot_name: String,
+    body: CreateRedactionBookmark,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.create_redaction_bookmark(&dataset, &snapshot_name, &body).await {
+        Ok(bookmark) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Redaction bookmark {} created", bookmark),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn inspect_snapshot_handler(
+    dataset: String,
+    snapshot_name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.inspect_snapshot(&dataset, &snapshot_name).await {
+        Ok((clone, mount_path)) => Ok(json_response(&InspectResponse {
+            clone,
+            mount_path,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn cleanup_inspection_handler(
+    dataset: String,
+    snapshot_name: String,
+    clone_name: String,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.cleanup_inspection(&dataset, &snapshot_name, &clone_name).await {
+        Ok(clone) => Ok(json_response(&ActionResponse {
+            status: "success".to_string(),
+            message: format!("Inspection clone {} removed", clone),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::{Rejection, Reply};
+
+use crate::audit::AuditLog;
+use crate::error::ZfsError;
+use crate::health::SelfCheck;
+use crate::models::*;
+use crate::zfs_management::ZfsManager;
+use super::{error_response, json_response};
+
+// Route handlers for request schemas
+pub async fn list_schemas_handler() -> Result<impl Reply, Rejection> {
+    Ok(json_response(&SchemaListResponse {
+        types: SCHEMA_TYPES.iter().map(|t| t.to_string()).collect(),
+        status: "success".to_string(),
+    }))
+}
+
+pub async fn get_schema_handler(type_name: String) -> Result<impl Reply, Rejection> {
+    match request_schema(&type_name) {
+        Some(schema) => Ok(json_response(&schema)),
+        None => Ok(error_response(&ZfsError::NotFound(format!(
+            "Unknown request type '{}'. Known types: {}",
+            type_name,
+            SCHEMA_TYPES.join(", ")
+        )))),
+    }
+}
+
+pub async fn diagnostics_handler(self_check: Arc<SelfCheck>) -> Result<impl Reply, Rejection> {
+    Ok(json_response(self_check.as_ref()))
+}
+
+// Liveness: the agent is up and serving requests; ZFS itself is not consulted
+pub async fn liveness_handler() -> Result<impl Reply, Rejection> {
+    Ok(json_response(&ActionResponse {
+        status: "success".to_string(),
+        message: "Agent is running".to_string(),
+    }))
+}
+
+// Readiness: 503 unless ZFS answers a pool listing, so orchestrators can hold traffic back
+pub async fn readiness_handler(zfs: ZfsManager) -> Result<impl Reply, Rejection> {
+    let (reply, code) = match zfs.list_pools().await {
+        Ok(pools) => {
+            let pool_count = pools.len();
+            let reply = ReadinessResponse {
+                ready: true,
+                pool_count,
+                unhealthy_pools: pools.into_iter().filter(|pool| pool.health != "ONLINE").collect(),
+                reason: None,
+                status: "success".to_string(),
+            };
+            (reply, StatusCode::OK)
+        }
+        Err(e) => {
+            let reply = ReadinessResponse {
+                ready: false,
+                pool_count: 0,
+                unhealthy_pools: Vec::new(),
+                reason: Some(e.to_string()),
+                status: "error".to_string(),
+            };
+            (reply, StatusCode::SERVICE_UNAVAILABLE)
+        }
+    };
+    Ok(warp::reply::with_status(json_response(&reply), code))
+}
+
+pub async fn arc_stats_handler(zfs: ZfsManager) -> Result<impl Reply, Rejection> {
+    match zfs.get_arc_stats().await {
+        Ok(arc) => Ok(json_response(&ArcStatsResponse {
+            arc,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+// Server-sent events for every ZFS event; dropping the connection stops the underlying reader
+pub async fn events_handler(zfs: ZfsManager) -> Result<warp::reply::Response, Rejection> {
+    let receiver = match zfs.subscribe_events().await {
+        Ok(receiver) => receiver,
+        Err(e) => return Ok(error_response(&e)),
+    };
+    let events = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        let sse = warp::sse::Event::default().event("zfs");
+        let sse = match event.eid {
+            Some(eid) => sse.id(eid.to_string()),
+            None => sse,
+        };
+        Some((sse.json_data(&event), receiver))
+    });
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(events)).into_response())
+}
+
+pub async fn audit_handler(query: AuditQuery, audit: AuditLog) -> Result<impl Reply, Rejection> {
+    match audit.recent(query.limit.unwrap_or(100)).await {
+        Ok(entries) => Ok(json_response(&AuditResponse {
+            entries,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
@@ -0,0 +1,37 @@
+use warp::{Rejection, Reply};
+
+use crate::models::*;
+use crate::zfs_management::ZfsManager;
+use super::{error_response, json_response};
+
+// Route handlers for volumes
+pub async fn create_volume_handler(
+    body: CreateVolumeRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.create_volume(&body).await {
+        Ok(device) => Ok(json_response(&VolumeResponse {
+            name: body.name,
+            device,
+            size_bytes: body.size_bytes,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
+
+pub async fn resize_volume_handler(
+    name: String,
+    body: ResizeVolumeRequest,
+    zfs: ZfsManager,
+) -> Result<impl Reply, Rejection> {
+    match zfs.resize_volume(&name, body.size_bytes).await {
+        Ok(device) => Ok(json_response(&VolumeResponse {
+            name,
+            device,
+            size_bytes: body.size_bytes,
+            status: "success".to_string(),
+        })),
+        Err(e) => Ok(error_response(&e)),
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::logging;
+use crate::zfs_management::run_command_blocking;
+
+// How often ZFS availability is re-probed, also advertised to clients as Retry-After
+pub const ZFS_PROBE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Tracks whether ZFS is currently usable so data routes can answer 503 instead of failing one by one
+#[derive(Clone)]
+pub struct ZfsHealth {
+    degraded: Arc<AtomicBool>,
+    reason: Arc<Mutex<Option<String>>>,
+}
+
+impl ZfsHealth {
+    pub fn new() -> Self {
+        ZfsHealth {
+            degraded: Arc::new(AtomicBool::new(false)),
+            reason: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    pub fn reason(&self) -> Option<String> {
+        self.reason.lock().unwrap().clone()
+    }
+
+    pub fn record_probe(&self, result: Result<(), String>) {
+        let mut reason = self.reason.lock().unwrap();
+        match result {
+            Ok(()) => {
+                if self.degraded.swap(false, Ordering::Relaxed) {
+                    logging::info("ZFS available again, leaving degraded mode");
+                }
+                *reason = None;
+            }
+            Err(e) => {
+                if !self.degraded.swap(true, Ordering::Relaxed) {
+                    logging::warn(&format!("ZFS unavailable, entering degraded mode: {}", e));
+                }
+                *reason = Some(e);
+            }
+        }
+    }
+}
+
+// A cheap end-to-end check that the kernel module and pool configuration are reachable
+pub fn probe_zfs() -> Result<(), String> {
+    if !std::path::Path::new("/dev/zfs").exists() {
+        return Err("/dev/zfs is missing (is the zfs module loaded?)".to_string());
+    }
+    run_command_blocking("zpool", &["list", "-H", "-o", "name"])
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug)]
+pub struct ZfsUnavailable;
+
+impl warp::reject::Reject for ZfsUnavailable {}
+
+// Environment the agent came up in, recorded once at startup
+#[derive(Serialize, Clone)]
+pub struct SelfCheck {
+    agent_version: String,
+    zfs_userland_version: Option<String>,
+    zfs_kmod_version: Option<String>,
+    kernel_module_loaded: bool,
+    dev_zfs_present: bool,
+    zfs_cli_available: bool,
+    zpool_cli_available: bool,
+    libzetta_engine_initialized: bool,
+    checked_at: u64,
+}
+
+// Probe what ZFS support this host actually provides; the ZFS engine has already initialized by now
+pub fn run_self_check() -> SelfCheck {
+    // `zfs version` prints the userland version first and the kernel module version second
+    let versions = run_command_blocking("zfs", &["version"]).ok();
+    let mut version_lines = versions.iter().flat_map(|v| v.lines()).map(|l| l.trim().to_string());
+
+    SelfCheck {
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        zfs_userland_version: version_lines.next(),
+        zfs_kmod_version: version_lines.next(),
+        kernel_module_loaded: std::path::Path::new("/sys/module/zfs").exists(),
+        dev_zfs_present: std::path::Path::new("/dev/zfs").exists(),
+        zfs_cli_available: versions.is_some(),
+        zpool_cli_available: run_command_blocking("zpool", &["list", "-H", "-o", "name"]).is_ok(),
+        libzetta_engine_initialized: true,
+        checked_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use std::io::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// One JSON object per line on stdout, so log aggregators can parse agent output without patterns
+
+#[derive(Serialize)]
+struct LogLine<'a> {
+    /// Milliseconds since the epoch
+    timestamp: u64,
+    level: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<f64>,
+}
+
+impl LogLine<'_> {
+    fn emit(&self) {
+        let Ok(line) = serde_json::to_string(self) else { return };
+        // Holding the lock keeps lines from concurrent requests whole
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", line);
+    }
+}
+
+fn entry(level: &str) -> LogLine<'_> {
+    LogLine {
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+        level,
+        message: None,
+        request_id: None,
+        method: None,
+        path: None,
+        status: None,
+        duration_ms: None,
+    }
+}
+
+pub fn info(message: &str) {
+    LogLine { message: Some(message), ..entry("info") }.emit();
+}
+
+pub fn warn(message: &str) {
+    LogLine { message: Some(message), ..entry("warn") }.emit();
+}
+
+// The access log line written for every HTTP request
+pub fn request(request_id: &str, method: &str, path: &str, status: u16, duration: Duration) {
+    LogLine {
+        request_id: Some(request_id),
+        method: Some(method),
+        path: Some(path),
+        status: Some(status),
+        duration_ms: Some(duration.as_secs_f64() * 1000.0),
+        ..entry(if status >= 500 { "error" } else { "info" })
+    }
+    .emit();
+}
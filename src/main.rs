@@ -1,229 +1,211 @@
 use warp::{Filter, Rejection, Reply};
-use serde::{Deserialize, Serialize};
-use libzetta::zfs::{
-    DelegatingZfsEngine, 
-    ZfsEngine,
-    CreateDatasetRequest, 
-    DatasetKind
-};
+use warp::http::StatusCode;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tokio;
-use std::path::PathBuf;
-use std::collections::HashMap;  // Add this at the top with other imports
-
-// Response structures
-#[derive(Serialize)]
-struct ListResponse {
-    snapshots: Vec<String>,
-    status: String,
-}
-
-#[derive(Serialize)]
-struct ActionResponse {
-    status: String,
-    message: String,
-}
-
-#[derive(Deserialize)]
-struct CreateSnapshot {
-    snapshot_name: String,
-}
-
-// Request/Response structures for datasets
-#[derive(Deserialize)]
-struct CreateDataset {
-    name: String,
-    kind: String,  // "filesystem" or "volume"
-    properties: Option<HashMap<String, String>>,
-}
 
-#[derive(Serialize)]
-struct DatasetResponse {
-    datasets: Vec<String>,
-    status: String,
-}
+mod alerts;
+mod audit;
+mod error;
+mod handlers;
+mod health;
+mod logging;
+mod models;
+mod zfs_management;
 
-// ZFS wrapper to make it easier to share between routes
-#[derive(Clone)]
-struct ZfsManager {
-    engine: Arc<DelegatingZfsEngine>,
-}
+use alerts::PoolAlerter;
+use audit::AuditLog;
+use handlers::bookmarks::*;
+use handlers::datasets::*;
+use handlers::disks::*;
+use handlers::pools::*;
+use handlers::snapshots::*;
+use handlers::system::*;
+use handlers::volumes::*;
+use health::{probe_zfs, run_self_check, ZfsHealth, ZfsUnavailable, ZFS_PROBE_INTERVAL};
+use models::*;
+use zfs_management::{blocking, ZfsManager};
 
-impl ZfsManager {
-    fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(ZfsManager {
-            engine: Arc::new(DelegatingZfsEngine::new()?),
+// Reject data routes while ZFS is degraded; the rejection is turned into a 503 by handle_rejection.
+// It is chained in with the manager filter, after each route's path filters.
+fn require_zfs(health: ZfsHealth) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::any()
+        .and_then(move || {
+            let health = health.clone();
+            async move {
+                if health.is_degraded() {
+                    Err(warp::reject::custom(ZfsUnavailable))
+                } else {
+                    Ok(())
+                }
+            }
         })
-    }
-
-    // List snapshots for a dataset
-    async fn list_snapshots(&self, dataset: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let snapshots = self.engine.list_snapshots(dataset)?;
-        Ok(snapshots
-            .into_iter()
-            .map(|p| p.to_string_lossy().into_owned())
-            .collect())
-    }
-
-    // Create a new snapshot
-    async fn create_snapshot(&self, dataset: &str, snapshot_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let full_path = PathBuf::from(format!("{}@{}", dataset, snapshot_name));
-        self.engine.snapshot(&[full_path], None)?;
-        Ok(())
-    }
-
-    // Delete a snapshot
-    async fn delete_snapshot(&self, dataset: &str, snapshot_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let full_path = PathBuf::from(format!("{}@{}", dataset, snapshot_name));
-        self.engine.destroy(full_path)?;
-        Ok(())
-    }
-
-    async fn list_datasets(&self, pool: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let datasets = self.engine.list_filesystems(pool)?;
-        Ok(datasets
-            .into_iter()
-            .map(|p| p.to_string_lossy().into_owned())
-            .collect())
-    }
-
-    async fn create_dataset(&self, request: CreateDataset) -> Result<(), Box<dyn std::error::Error>> {
-        let kind = match request.kind.to_lowercase().as_str() {
-            "filesystem" => DatasetKind::Filesystem,
-            "volume" => DatasetKind::Volume,
-            _ => return Err("Invalid dataset kind. Must be 'filesystem' or 'volume'".into()),
-        };
-
-        let dataset_request = CreateDatasetRequest::builder()
-            .name(PathBuf::from(request.name))
-            .kind(kind)
-            .user_properties(request.properties)
-            .build()?;
-
-        self.engine.create(dataset_request)?;
-        Ok(())
-    }
-
-    async fn delete_dataset(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.engine.destroy(name)?;
-        Ok(())
-    }
-
+        .untuple_one()
 }
 
-// Route handlers
-async fn list_snapshots_handler(
-    dataset: String,
-    zfs: ZfsManager,
-) -> Result<impl Reply, Rejection> {
-    match zfs.list_snapshots(&dataset).await {
-        Ok(snapshots) => Ok(warp::reply::json(&ListResponse {
-            snapshots,
-            status: "success".to_string(),
-        })),
-        Err(e) => Ok(warp::reply::json(&ActionResponse {
+async fn handle_rejection(err: Rejection, health: ZfsHealth) -> Result<impl Reply, Rejection> {
+    if err.find::<ZfsUnavailable>().is_some() {
+        let reason = health.reason().unwrap_or_else(|| "unknown error".to_string());
+        let reply = warp::reply::json(&ActionResponse {
             status: "error".to_string(),
-            message: e.to_string(),
-        })),
+            message: format!("ZFS is currently unavailable: {}", reason),
+        });
+        let reply = warp::reply::with_status(reply, StatusCode::SERVICE_UNAVAILABLE);
+        return Ok(warp::reply::with_header(reply, "retry-after", ZFS_PROBE_INTERVAL.as_secs().to_string()).into_response());
     }
+
+    // Malformed input and unknown routes get the same error body and codes as handler failures.
+    // warp reports a wrong method on any route even when no path matched, so both count as not found.
+    let (status, code, message) = if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, "invalid_argument", format!("Invalid request body: {}", e))
+    } else if let Some(e) = err.find::<warp::reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, "invalid_argument", e.to_string())
+    } else if err.is_not_found() || err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (StatusCode::NOT_FOUND, "not_found", "No such endpoint".to_string())
+    } else {
+        return Err(err);
+    };
+    let reply = warp::reply::json(&ErrorResponse {
+        status: "error".to_string(),
+        message,
+        code: code.to_string(),
+        resume_token: None,
+    });
+    Ok(warp::reply::with_status(reply, status).into_response())
 }
 
-async fn create_snapshot_handler(
-    dataset: String,
-    body: CreateSnapshot,
-    zfs: ZfsManager,
-) -> Result<impl Reply, Rejection> {
-    match zfs.create_snapshot(&dataset, &body.snapshot_name).await {
-        Ok(_) => Ok(warp::reply::json(&ActionResponse {
-            status: "success".to_string(),
-            message: "Snapshot created successfully".to_string(),
-        })),
-        Err(e) => Ok(warp::reply::json(&ActionResponse {
-            status: "error".to_string(),
-            message: e.to_string(),
-        })),
-    }
+// Accept a caller-supplied X-Request-Id if it is a sane token, otherwise generate one
+fn request_id() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    warp::header::optional::<String>("x-request-id").map(|inbound: Option<String>| {
+        inbound
+            .filter(|id| {
+                !id.is_empty()
+                    && id.len() <= 128
+                    && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+            })
+            .unwrap_or_else(|| {
+                let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+                format!("{:x}-{:x}", nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+            })
+    })
 }
 
-async fn delete_snapshot_handler(
-    dataset: String,
-    snapshot_name: String,
-    zfs: ZfsManager,
-) -> Result<impl Reply, Rejection> {
-    match zfs.delete_snapshot(&dataset, &snapshot_name).await {
-        Ok(_) => Ok(warp::reply::json(&ActionResponse {
-            status: "success".to_string(),
-            message: "Snapshot deleted successfully".to_string(),
-        })),
-        Err(e) => Ok(warp::reply::json(&ActionResponse {
-            status: "error".to_string(),
-            message: e.to_string(),
-        })),
-    }
+// Match a tail path ending in an action suffix (e.g. "/labelclear"), yielding the part before it
+fn tail_with_suffix(suffix: &'static str) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::path::tail().and_then(move |tail: warp::path::Tail| async move {
+        match tail.as_str().strip_suffix(suffix) {
+            Some(prefix) if !prefix.is_empty() => Ok(prefix.to_string()),
+            _ => Err(warp::reject::not_found()),
+        }
+    })
 }
 
-// Route handlers for datasets
-async fn list_datasets_handler(
-    pool: String,
-    zfs: ZfsManager,
-) -> Result<impl Reply, Rejection> {
-    match zfs.list_datasets(&pool).await {
-        Ok(datasets) => Ok(warp::reply::json(&DatasetResponse {
-            datasets,
-            status: "success".to_string(),
-        })),
-        Err(e) => Ok(warp::reply::json(&ActionResponse {
-            status: "error".to_string(),
-            message: e.to_string(),
-        })),
+// TLS is enabled only when both a certificate and a key path are configured
+fn tls_paths() -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
+    let cert = std::env::var("ZFS_AGENT_TLS_CERT").ok().filter(|v| !v.is_empty());
+    let key = std::env::var("ZFS_AGENT_TLS_KEY").ok().filter(|v| !v.is_empty());
+    match (cert, key) {
+        (Some(cert), Some(key)) => {
+            for path in [&cert, &key] {
+                if !std::path::Path::new(path).is_file() {
+                    return Err(format!("TLS file '{}' does not exist", path).into());
+                }
+            }
+            Ok(Some((cert, key)))
+        }
+        (None, None) => Ok(None),
+        _ => Err("ZFS_AGENT_TLS_CERT and ZFS_AGENT_TLS_KEY must be set together".into()),
     }
 }
 
-async fn create_dataset_handler(
-    body: CreateDataset,
-    zfs: ZfsManager,
-) -> Result<impl Reply, Rejection> {
-    match zfs.create_dataset(body).await {
-        Ok(_) => Ok(warp::reply::json(&ActionResponse {
-            status: "success".to_string(),
-            message: "Dataset created successfully".to_string(),
-        })),
-        Err(e) => Ok(warp::reply::json(&ActionResponse {
-            status: "error".to_string(),
-            message: e.to_string(),
-        })),
-    }
+// Listen address from ZFS_AGENT_BIND (default 0.0.0.0) and ZFS_AGENT_PORT (default 9876).
+// 0.0.0.0 exposes the agent on every interface; set ZFS_AGENT_BIND=127.0.0.1 for loopback only.
+fn listen_addr() -> Result<SocketAddr, Box<dyn std::error::Error>> {
+    let bind = std::env::var("ZFS_AGENT_BIND").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let port = std::env::var("ZFS_AGENT_PORT").unwrap_or_else(|_| "9876".to_string());
+
+    let ip: IpAddr = bind
+        .trim()
+        .parse()
+        .map_err(|_| format!("ZFS_AGENT_BIND '{}' is not a valid IP address", bind))?;
+    let port: u16 = port
+        .trim()
+        .parse()
+        .map_err(|_| format!("ZFS_AGENT_PORT '{}' is not a valid port", port))?;
+    Ok(SocketAddr::new(ip, port))
 }
 
-async fn delete_dataset_handler(
-    name: String,
-    zfs: ZfsManager,
-) -> Result<impl Reply, Rejection> {
-    match zfs.delete_dataset(&name).await {
-        Ok(_) => Ok(warp::reply::json(&ActionResponse {
-            status: "success".to_string(),
-            message: "Dataset deleted successfully".to_string(),
-        })),
-        Err(e) => Ok(warp::reply::json(&ActionResponse {
-            status: "error".to_string(),
-            message: e.to_string(),
-        })),
-    }
+// Match a tail of the form "{path}/{marker}/{name}", yielding the path and the name
+fn tail_with_named_suffix(marker: &'static str) -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+    warp::path::tail()
+        .and_then(move |tail: warp::path::Tail| async move {
+            let parsed = tail.as_str().rsplit_once('/').and_then(|(prefix, name)| {
+                let path = prefix.strip_suffix(marker)?.strip_suffix('/')?;
+                (!path.is_empty() && !name.is_empty()).then(|| (path.to_string(), name.to_string()))
+            });
+            parsed.ok_or_else(warp::reject::not_found)
+        })
+        .untuple_one()
 }
 
-// Main function
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let addr = listen_addr()?;
+    let tls = tls_paths()?;
+
     // Initialize ZFS manager
-    let zfs = ZfsManager::new()?;
-    let zfs = warp::any().map(move || zfs.clone());
+    let manager = ZfsManager::new()?;
+    let alerter = PoolAlerter::from_env()?;
+    let zfs = {
+        let manager = manager.clone();
+        warp::any().map(move || manager.clone())
+    };
+
+    let audit = AuditLog::from_env();
+    logging::info(&format!("Recording mutating requests in {}", audit.path().display()));
+
+    let self_check = Arc::new(blocking(run_self_check).await);
+    logging::info(&format!("Startup self-check: {}", serde_json::to_string(self_check.as_ref())?));
+
+    // Periodically re-probe ZFS so data routes can back off with 503 during outages
+    let health = ZfsHealth::new();
+    {
+        let health = health.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ZFS_PROBE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let result = tokio::task::spawn_blocking(probe_zfs)
+                    .await
+                    .unwrap_or_else(|e| Err(e.to_string()));
+                health.record_probe(result);
+            }
+        });
+    }
+
+    // Data routes take the manager through this filter, so the health check only runs once a route's
+    // path has matched; unknown paths still get a 404 while ZFS is degraded
+    let ready_zfs = zfs.clone();
+    let zfs = require_zfs(health.clone()).and(zfs);
 
     // Define routes
     // Snapshot routes (your existing routes)
     let snapshot_routes = {
+        let search = warp::get()
+            .and(warp::path("snapshots"))
+            .and(warp::path::end())
+            .and(warp::query::<SnapshotSearchQuery>())
+            .and(zfs.clone())
+            .and_then(search_snapshots_handler);
+
         let list = warp::get()
             .and(warp::path("snapshots"))
             .and(warp::path::param())
+            .and(warp::query::<SnapshotListQuery>())
             .and(zfs.clone())
             .and_then(list_snapshots_handler);
 
@@ -238,9 +220,181 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .and(warp::path("snapshots"))
             .and(warp::path::param())
             .and(warp::path::param())
+            .and(warp::path::end())
             .and(zfs.clone())
             .and_then(delete_snapshot_handler);
 
+        let redact = warp::post()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("redact"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(create_redaction_bookmark_handler);
+
+        let rename = warp::post()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("rename"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(rename_snapshot_handler);
+
+        let hold = warp::post()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("holds"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(hold_snapshot_handler);
+
+        let release = warp::delete()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("holds"))
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(release_hold_handler);
+
+        // Must be tried before `list`, which matches any GET under /snapshots/{dataset}
+        let holds = warp::get()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("holds"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(list_holds_handler);
+
+        // Like `holds`, must be tried before `list`
+        let deltas = warp::get()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path("deltas"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(snapshot_deltas_handler);
+
+        // Like `holds`, must be tried before `list`
+        let clones = warp::get()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("clones"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(list_clones_handler);
+
+        // Like `holds`, must be tried before `list`
+        let stream = warp::get()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("stream"))
+            .and(warp::path::end())
+            .and(warp::query::<SendStreamQuery>())
+            .and(zfs.clone())
+            .and_then(send_stream_handler);
+
+        // Like `holds`, must be tried before `list`
+        let diff = warp::get()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("diff"))
+            .and(warp::path::end())
+            .and(warp::query::<DiffQuery>())
+            .and(zfs.clone())
+            .and_then(diff_snapshot_handler);
+
+        let inspect = warp::post()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("inspect"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(inspect_snapshot_handler);
+
+        let inspect_cleanup = warp::delete()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("inspect"))
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(cleanup_inspection_handler);
+
+        let send = warp::post()
+            .and(warp::path("snapshots"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path("send"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(send_snapshot_handler);
+
+        let batch = warp::post()
+            .and(warp::path("snapshots"))
+            .and(warp::path("batch"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(batch_snapshot_handler);
+
+        search
+            .or(holds)
+            .or(deltas)
+            .or(clones)
+            .or(stream)
+            .or(diff)
+            .or(list)
+            .or(batch)
+            .or(send)
+            .or(redact)
+            .or(rename)
+            .or(hold)
+            .or(release)
+            .or(inspect)
+            .or(inspect_cleanup)
+            .or(create)
+            .or(delete)
+    };
+
+    let bookmark_routes = {
+        let list = warp::get()
+            .and(warp::path("bookmarks"))
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(list_bookmarks_handler);
+
+        let create = warp::post()
+            .and(warp::path("bookmarks"))
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(create_bookmark_handler);
+
+        let delete = warp::delete()
+            .and(warp::path("bookmarks"))
+            .and(warp::path::param())
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(destroy_bookmark_handler);
+
         list.or(create).or(delete)
     };
 
@@ -248,8 +402,59 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let list = warp::get()
             .and(warp::path("datasets"))
             .and(warp::path::param())
+            .and(warp::query::<DatasetListQuery>())
             .and(zfs.clone())
             .and_then(list_datasets_handler);
+
+        let by_mountpoint = warp::get()
+            .and(warp::path("datasets"))
+            .and(warp::path("by-mountpoint"))
+            .and(warp::path::end())
+            .and(warp::query::<MountpointQuery>())
+            .and(zfs.clone())
+            .and_then(dataset_by_mountpoint_handler);
+
+        // Must be tried before `list`, which matches any GET under /datasets/{pool}
+        let get_property = warp::get()
+            .and(warp::path("datasets"))
+            .and(tail_with_named_suffix("properties"))
+            .and(zfs.clone())
+            .and_then(get_property_handler);
+
+        // Must be tried before `list` as well
+        let get_exists = warp::get()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/exists"))
+            .and(zfs.clone())
+            .and_then(get_dataset_exists_handler);
+
+        // Must be tried before `list` as well
+        let tree = warp::get()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/tree"))
+            .and(zfs.clone())
+            .and_then(dataset_tree_handler);
+
+        // Must be tried before `list` as well
+        let origin = warp::get()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/origin"))
+            .and(zfs.clone())
+            .and_then(get_origin_handler);
+
+        let space = warp::get()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/space"))
+            .and(zfs.clone())
+            .and_then(space_usage_handler);
+
+        let batch_properties = warp::post()
+            .and(warp::path("datasets"))
+            .and(warp::path("properties"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(batch_properties_handler);
     
         // New delete route implementation
         let delete = warp::delete()
@@ -266,14 +471,596 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .and(zfs.clone())
             .and_then(create_dataset_handler);
     
-        list.or(create).or(delete)
+        let receive = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/receive"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(receive_snapshot_handler);
+
+        let receive_stream = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/receive-stream"))
+            .and(warp::query::<ReceiveStreamQuery>())
+            .and(warp::body::stream())
+            .and(zfs.clone())
+            .and_then(receive_stream_handler);
+
+        let rename = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/rename"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(rename_dataset_handler);
+
+        let mount = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/mount"))
+            .and(zfs.clone())
+            .and_then(mount_dataset_handler);
+
+        let unmount = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/unmount"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(unmount_dataset_handler);
+
+        let prune_snapshots = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/snapshots/prune"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(prune_snapshots_handler);
+
+        let load_key = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/load-key"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(load_key_handler);
+
+        let unload_key = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/unload-key"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(unload_key_handler);
+
+        let change_key = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/change-key"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(change_key_handler);
+
+        let set_property = warp::put()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/properties"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(set_dataset_property_handler);
+
+        // Must be tried before `delete`, which treats the whole tail as a dataset name
+        let inherit_property = warp::delete()
+            .and(warp::path("datasets"))
+            .and(tail_with_named_suffix("properties"))
+            .and(warp::query::<InheritQuery>())
+            .and(zfs.clone())
+            .and_then(inherit_property_handler);
+
+        let quota = warp::put()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/quota"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(set_quota_handler);
+
+        let snapdir = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/snapdir"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(snapdir_handler);
+
+        let share = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/share"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(share_dataset_handler);
+
+        // Must be tried before `delete`, which treats the whole tail as a dataset name
+        let unshare = warp::delete()
+            .and(warp::path("datasets"))
+            .and(tail_with_named_suffix("share"))
+            .and(zfs.clone())
+            .and_then(unshare_dataset_handler);
+
+        let delete_preview = warp::post()
+            .and(warp::path("datasets"))
+            .and(tail_with_suffix("/snapshot-delete-preview"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(snapshot_delete_preview_handler);
+
+        let exists = warp::head()
+            .and(warp::path("datasets"))
+            .and(warp::path::tail())
+            .and(zfs.clone())
+            .and_then(|tail: warp::path::Tail, zfs: ZfsManager| {
+                dataset_exists_handler(tail.as_str().to_string(), zfs)
+            });
+
+        by_mountpoint
+            .or(get_property)
+            .or(get_exists)
+            .or(origin)
+            .or(space)
+            .or(tree)
+            .or(list)
+            .or(batch_properties)
+            .or(receive)
+            .or(receive_stream)
+            .or(rename)
+            .or(mount)
+            .or(unmount)
+            .or(prune_snapshots)
+            .or(load_key)
+            .or(unload_key)
+            .or(change_key)
+            .or(set_property)
+            .or(inherit_property)
+            .or(quota)
+            .or(snapdir)
+            .or(share)
+            .or(unshare)
+            .or(delete_preview)
+            .or(create)
+            .or(delete)
+            .or(exists)
     };
 
-    // Combine all routes
-    let routes = snapshot_routes.or(dataset_routes);
+    let pool_routes = {
+        let importable = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path("importable"))
+            .and(warp::path::end())
+            .and(warp::query::<ImportableQuery>())
+            .and(zfs.clone())
+            .and_then(list_importable_pools_handler);
+
+        let import = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path("import"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(import_pool_handler);
+
+        let exists = warp::head()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(pool_exists_handler);
+
+        let get_exists = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("exists"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(get_pool_exists_handler);
+
+        let status = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(warp::query::<PoolStatusQuery>())
+            .and(zfs.clone())
+            .and_then(pool_status_handler);
+
+        let scrub_status = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("scrub"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(scrub_status_handler);
+
+        let resilver_status = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("resilver"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(resilver_status_handler);
+
+        let history = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("history"))
+            .and(warp::path::end())
+            .and(warp::query::<PoolHistoryQuery>())
+            .and(zfs.clone())
+            .and_then(pool_history_handler);
+
+        let get_properties = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("properties"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(get_pool_properties_handler);
+
+        let set_property = warp::put()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("properties"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(set_pool_property_handler);
+
+        let replace_vdev = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("vdev"))
+            .and(warp::path("replace"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(replace_vdev_handler);
+
+        let attach_vdev = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("vdev"))
+            .and(warp::path("attach"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(attach_vdev_handler);
+
+        let detach_vdev = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("vdev"))
+            .and(warp::path("detach"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(detach_vdev_handler);
+
+        let offline_vdev = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("vdev"))
+            .and(tail_with_suffix("/offline"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(offline_vdev_handler);
+
+        let online_vdev = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("vdev"))
+            .and(tail_with_suffix("/online"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(online_vdev_handler);
 
-    println!("Server starting on port 9876");
-    warp::serve(routes).run(([0, 0, 0, 0], 9876)).await;
+        let split = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("split"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(split_pool_handler);
+
+        let clear = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("clear"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(clear_pool_handler);
+
+        let checkpoint = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("checkpoint"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(checkpoint_pool_handler);
+
+        let discard_checkpoint = warp::delete()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("checkpoint"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(discard_checkpoint_handler);
+
+        let features = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("features"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(pool_features_handler);
+
+        let upgrade = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("upgrade"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(upgrade_pool_handler);
+
+        let expand = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("expand"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(expand_pool_handler);
+
+        let reguid = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("reguid"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(reguid_pool_handler);
+
+        let start_trim = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("trim"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(start_trim_handler);
+
+        let cancel_trim = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("trim"))
+            .and(warp::path("cancel"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(cancel_trim_handler);
+
+        let trim_status = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("trim"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(trim_status_handler);
+
+        let start_initialize = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("initialize"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(start_initialize_handler);
+
+        let cancel_initialize = warp::post()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("initialize"))
+            .and(warp::path("cancel"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(cancel_initialize_handler);
+
+        let initialize_status = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("initialize"))
+            .and(warp::path::end())
+            .and(zfs.clone())
+            .and_then(initialize_status_handler);
+
+        let iostat = warp::get()
+            .and(warp::path("pools"))
+            .and(warp::path::param())
+            .and(warp::path("iostat"))
+            .and(warp::path::end())
+            .and(warp::query::<IostatQuery>())
+            .and(zfs.clone())
+            .and_then(pool_iostat_handler);
+
+        importable
+            .or(import)
+            .or(exists)
+            .or(get_exists)
+            .or(status)
+            .or(scrub_status)
+            .or(resilver_status)
+            .or(history)
+            .or(get_properties)
+            .or(set_property)
+            .or(replace_vdev)
+            .or(attach_vdev)
+            .or(detach_vdev)
+            .or(offline_vdev)
+            .or(online_vdev)
+            .or(start_trim)
+            .or(cancel_trim)
+            .or(trim_status)
+            .or(start_initialize)
+            .or(cancel_initialize)
+            .or(initialize_status)
+            .or(iostat)
+            .or(expand)
+            .or(reguid)
+            .or(clear)
+            .or(split)
+            .or(checkpoint)
+            .or(discard_checkpoint)
+            .or(features)
+            .or(upgrade)
+    };
+
+    let volume_routes = {
+        let create = warp::post()
+            .and(warp::path("volumes"))
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(create_volume_handler);
+
+        let resize = warp::put()
+            .and(warp::path("volumes"))
+            .and(tail_with_suffix("/size"))
+            .and(warp::body::json())
+            .and(zfs.clone())
+            .and_then(resize_volume_handler);
+
+        create.or(resize)
+    };
+
+    let disk_routes = warp::post()
+        .and(warp::path("disks"))
+        .and(tail_with_suffix("/labelclear"))
+        .and(warp::body::json())
+        .and(zfs.clone())
+        .and_then(labelclear_handler);
+
+    let schema_routes = {
+        let list = warp::get()
+            .and(warp::path("schema"))
+            .and(warp::path::end())
+            .and_then(list_schemas_handler);
+
+        let get = warp::get()
+            .and(warp::path("schema"))
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and_then(get_schema_handler);
+
+        list.or(get)
+    };
+
+    let arc_route = warp::get()
+        .and(warp::path("arc"))
+        .and(warp::path::end())
+        .and(zfs.clone())
+        .and_then(arc_stats_handler);
+
+    let events_route = warp::get()
+        .and(warp::path("events"))
+        .and(warp::path::end())
+        .and(zfs.clone())
+        .and_then(events_handler);
+
+    let audit_route = {
+        let audit = audit.clone();
+        warp::get()
+            .and(warp::path("audit"))
+            .and(warp::path::end())
+            .and(warp::query::<AuditQuery>())
+            .and(warp::any().map(move || audit.clone()))
+            .and_then(audit_handler)
+    };
+
+    let diagnostics_route = warp::get()
+        .and(warp::path("diagnostics"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || self_check.clone()))
+        .and_then(diagnostics_handler);
+
+    // Liveness and readiness probes; readiness checks ZFS itself, so neither sits behind require_zfs
+    let probe_routes = {
+        let health = warp::get()
+            .and(warp::path("health"))
+            .and(warp::path::end())
+            .and_then(liveness_handler);
+
+        let ready = warp::get()
+            .and(warp::path("ready"))
+            .and(warp::path::end())
+            .and(ready_zfs)
+            .and_then(readiness_handler);
+
+        health.or(ready)
+    };
+
+    // Combine all routes; schema, diagnostics and probes stay reachable while ZFS is degraded
+    let data_routes = snapshot_routes
+        .or(bookmark_routes)
+        .or(dataset_routes)
+        .or(pool_routes)
+        .or(volume_routes)
+        .or(disk_routes)
+        .or(arc_route)
+        .or(events_route);
+
+    let rejection_health = health.clone();
+    let routes = data_routes
+        .or(schema_routes)
+        .or(diagnostics_route)
+        .or(audit_route)
+        .or(probe_routes)
+        .recover(move |err| handle_rejection(err, rejection_health.clone()));
+
+    // Tag every response with its request id and write a JSON access log line for it, so UI actions
+    // can be traced to log lines. Mutating requests are also appended to the audit log.
+    let routes = request_id()
+        .and(warp::method())
+        .and(warp::path::full())
+        .and(warp::any().map(Instant::now))
+        .and(routes)
+        .then(move |id: String, method: warp::http::Method, path: warp::path::FullPath, started: Instant, reply| {
+            let audit = audit.clone();
+            async move {
+                let mut response = Reply::into_response(reply);
+                let status = response.status().as_u16();
+                logging::request(&id, method.as_str(), path.as_str(), status, started.elapsed());
+                if AuditLog::is_mutating(&method) {
+                    audit.record(&id, method.as_str(), path.as_str(), status).await;
+                }
+                if let Ok(value) = warp::http::HeaderValue::from_str(&id) {
+                    response.headers_mut().insert("x-request-id", value);
+                }
+                response
+            }
+        });
+
+    // Post to ZFS_AGENT_WEBHOOK whenever a pool leaves the ONLINE state
+    if let Some(mut alerter) = alerter {
+        logging::info(&format!("Sending pool health alerts to {}", alerter.webhook()));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(alerter.interval);
+            loop {
+                interval.tick().await;
+                alerter.poll(&manager).await;
+            }
+        });
+    }
+
+    // warp panics when it cannot bind, so check the address up front for a readable error
+    std::net::TcpListener::bind(addr).map_err(|e| format!("Cannot listen on {}: {}", addr, e))?;
+
+    match tls {
+        Some((cert, key)) => {
+            logging::info(&format!("Server starting on {} with TLS (certificate {})", addr, cert));
+            warp::serve(routes).tls().cert_path(cert).key_path(key).run(addr).await;
+        }
+        None => {
+            logging::info(&format!("Server starting on {} without TLS; set ZFS_AGENT_TLS_CERT and ZFS_AGENT_TLS_KEY to enable HTTPS", addr));
+            warp::serve(routes).run(addr).await;
+        }
+    }
 
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,1161 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use schemars::{schema_for, JsonSchema};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::audit::AuditEntry;
+
+// Response structures
+#[derive(Serialize)]
+pub struct ListResponse {
+    pub snapshots: Vec<String>,
+    /// Number of snapshots before pagination
+    pub total: usize,
+    pub has_more: bool,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct ActionResponse {
+    pub status: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ExistsResponse {
+    pub name: String,
+    pub exists: bool,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct ErrorResponse {
+    pub status: String,
+    pub message: String,
+    /// Stable error class, e.g. "not_found" or "busy"
+    pub code: String,
+    /// Set when a resumable receive failed partway; pass it to a send as resume_token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resume_token: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateSnapshot {
+    pub snapshot_name: String,
+    /// Also snapshot every descendant dataset, atomically with the parent
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Serialize)]
+pub struct CreateSnapshotResponse {
+    pub status: String,
+    pub message: String,
+    /// Full dataset@snapshot paths that were created
+    pub snapshots: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RenameSnapshot {
+    /// New snapshot name, without the dataset prefix
+    pub new_name: String,
+}
+
+#[derive(Serialize)]
+pub struct RenameSnapshotResponse {
+    /// Full dataset@snapshot path after the rename
+    pub snapshot: String,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct HoldRequest {
+    /// User hold tag; the snapshot cannot be destroyed while any tag is held
+    pub tag: String,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotHold {
+    pub tag: String,
+    /// Creation time as reported by zfs holds
+    pub created: String,
+}
+
+#[derive(Serialize)]
+pub struct ClonesResponse {
+    pub snapshot: String,
+    /// Datasets cloned from the snapshot; it cannot be destroyed while any exist
+    pub clones: Vec<String>,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct HoldsResponse {
+    pub snapshot: String,
+    pub holds: Vec<SnapshotHold>,
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct SnapshotListQuery {
+    /// Return SnapshotInfo entries instead of bare names
+    #[serde(default)]
+    pub detailed: bool,
+    /// With detailed, include snapshots of descendant datasets
+    #[serde(default)]
+    pub recursive: bool,
+    /// Page size; defaults to 500
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+    /// "name" (default) or "creation"
+    pub sort: Option<String>,
+    /// "asc" (default) or "desc"
+    pub order: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    /// Seconds since the epoch
+    pub creation: u64,
+    /// Bytes unique to this snapshot
+    pub used: u64,
+    pub referenced: u64,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotDelta {
+    pub name: String,
+    /// Seconds since the epoch
+    pub creation: u64,
+    /// Bytes freed if only this snapshot were destroyed
+    pub used: u64,
+    /// Bytes written to the dataset between the previous snapshot and this one
+    pub written: u64,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotDeltasResponse {
+    pub dataset: String,
+    /// Oldest first
+    pub snapshots: Vec<SnapshotDelta>,
+    pub status: String,
+}
+
+// Search for snapshots across datasets; every filter is optional
+#[derive(Deserialize)]
+pub struct SnapshotSearchQuery {
+    /// Pool (or dataset) to search below; all pools when omitted
+    pub pool: Option<String>,
+    /// Substring the snapshot name (the part after '@') must contain
+    pub contains: Option<String>,
+    /// Only snapshots created at least this many seconds ago
+    pub older_than: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotMatch {
+    pub dataset: String,
+    pub name: String,
+    /// Seconds since the epoch
+    pub creation: u64,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotSearchResponse {
+    pub snapshots: Vec<SnapshotMatch>,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct DetailedListResponse {
+    pub snapshots: Vec<SnapshotInfo>,
+    pub total: usize,
+    pub has_more: bool,
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    /// Later snapshot ("snap" on the same dataset or "dataset@snap"); the live filesystem when omitted
+    pub to: Option<String>,
+}
+
+// One line of the NDJSON body returned by GET /snapshots/{dataset}/{snapshot}/diff
+#[derive(Serialize)]
+pub struct DiffEntry {
+    /// modified, created, removed or renamed
+    pub change_type: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_path: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BatchSnapshotItem {
+    pub dataset: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BatchSnapshotRequest {
+    pub snapshots: Vec<BatchSnapshotItem>,
+}
+
+#[derive(Serialize)]
+pub struct BatchSnapshotResult {
+    pub snapshot: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BatchSnapshotResponse {
+    pub results: Vec<BatchSnapshotResult>,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateRedactionBookmark {
+    /// Name of the redaction bookmark to create on the snapshot's dataset
+    pub bookmark: String,
+    /// Snapshots of clones of this snapshot whose modified blocks are omitted from redacted sends
+    pub redaction_snapshots: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateBookmark {
+    /// Snapshot on the same dataset to bookmark
+    pub snapshot: String,
+    /// Bookmark name, without the dataset prefix
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct BookmarkListResponse {
+    pub bookmarks: Vec<String>,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct InspectResponse {
+    pub clone: String,
+    pub mount_path: String,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RemoteFileTarget {
+    /// SSH destination, e.g. "backup@storage.example.com"
+    pub remote_host: String,
+    /// Path of the stream file on the remote host
+    pub remote_path: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SendSnapshot {
+    /// Absolute path of a new stream file on the agent host, inside the send directory
+    /// (ZFS_AGENT_SEND_DIR); existing files are never overwritten
+    pub output_file: Option<String>,
+    /// Write the stream to a file on another host over ssh instead of locally
+    pub remote_file: Option<RemoteFileTarget>,
+    /// Send only the changes since this snapshot or bookmark ("snap", "dataset@snap", "#bookmark" or "dataset#bookmark")
+    pub from_snapshot: Option<String>,
+    /// With from_snapshot, also send every snapshot in between (zfs send -I instead of -i)
+    #[serde(default)]
+    pub include_intermediate: bool,
+    /// Include dataset properties in the stream (zfs send -p)
+    #[serde(default)]
+    pub properties: bool,
+    /// Emit WRITE_EMBEDDED records (zfs send -e); defaults to ZFS_AGENT_SEND_EMBED_DATA
+    pub embed_data: Option<bool>,
+    /// Allow blocks larger than 128K (zfs send -L); defaults to ZFS_AGENT_SEND_LARGE_BLOCKS
+    pub large_blocks: Option<bool>,
+    /// Send a redacted stream using this redaction bookmark of the snapshot (zfs send --redact)
+    pub redact_bookmark: Option<String>,
+    /// Send encrypted datasets as stored, without decrypting (zfs send -w)
+    #[serde(default)]
+    pub raw: bool,
+    /// Keep on-disk compression in the stream (zfs send -c)
+    #[serde(default)]
+    pub compressed: bool,
+    /// Replication stream of the dataset and every descendant with properties, snapshots and
+    /// clones (zfs send -R); children without this snapshot are skipped
+    #[serde(default)]
+    pub recursive: bool,
+    /// Resume an interrupted send (zfs send -t) with the token reported by the failed receive.
+    /// Tokens belong to the partially received dataset on the receiving pool, and the token already
+    /// names the snapshot and stream flags, so no other send options may be given.
+    pub resume_token: Option<String>,
+    /// Cap the average transfer rate so a send does not saturate the link
+    pub rate_limit_bytes_per_sec: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct SendSnapshotResponse {
+    pub message: String,
+    pub bytes_sent: u64,
+    pub average_bytes_per_sec: u64,
+    /// For recursive sends, every snapshot the replication stream carried
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub datasets: Vec<String>,
+    pub status: String,
+}
+
+// Query flags for streaming a send straight into the HTTP response
+#[derive(Deserialize)]
+pub struct SendStreamQuery {
+    pub from: Option<String>,
+    #[serde(default)]
+    pub include_intermediate: bool,
+    #[serde(default)]
+    pub properties: bool,
+    #[serde(default)]
+    pub raw: bool,
+    #[serde(default)]
+    pub compressed: bool,
+    #[serde(default)]
+    pub recursive: bool,
+    pub embed_data: Option<bool>,
+    pub large_blocks: Option<bool>,
+    pub resume_token: Option<String>,
+    pub rate_limit_bytes_per_sec: Option<u64>,
+}
+
+// Request/Response structures for datasets
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateDataset {
+    pub name: String,
+    /// "filesystem" or "volume"
+    pub kind: String,
+    pub properties: Option<HashMap<String, String>>,
+    /// Create missing ancestor filesystems first, like zfs create -p; properties apply only to the leaf
+    #[serde(default)]
+    pub create_parents: bool,
+    /// Make the dataset an encryption root, e.g. "aes-256-gcm" or "on"
+    pub encryption: Option<String>,
+    /// passphrase, hex or raw; required with encryption
+    pub keyformat: Option<String>,
+    /// "prompt" (the default, key given in `key`) or a file:// or https:// URL
+    pub keylocation: Option<String>,
+    /// Key material for keylocation=prompt; passed to zfs on stdin and never logged
+    pub key: Option<String>,
+}
+
+// With discard_first_element or use_last_element, the target dataset is the parent under which
+// the stream's own dataset name is recreated rather than the exact dataset to receive into
+#[derive(Deserialize, JsonSchema)]
+pub struct ReceiveSnapshot {
+    /// Absolute path of the stream file on the agent host, inside the send directory (ZFS_AGENT_SEND_DIR)
+    pub input_file: String,
+    /// Roll back the target to its latest snapshot before receiving (zfs receive -F)
+    #[serde(default)]
+    pub force: bool,
+    /// Append only the last element of the sent dataset name to the target (zfs receive -e)
+    #[serde(default)]
+    pub use_last_element: bool,
+    /// Append the sent dataset name minus its pool to the target (zfs receive -d)
+    #[serde(default)]
+    pub discard_first_element: bool,
+    /// Keep partial state if the receive is interrupted (zfs receive -s), so the error
+    /// carries a resume token for the sender
+    #[serde(default)]
+    pub resumable: bool,
+}
+
+// Receive flags for POST /datasets/{path}/receive-stream, where the body is the stream itself
+#[derive(Deserialize)]
+pub struct ReceiveStreamQuery {
+    #[serde(default)]
+    pub force: bool,
+    #[serde(default)]
+    pub use_last_element: bool,
+    #[serde(default)]
+    pub discard_first_element: bool,
+    #[serde(default)]
+    pub resumable: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct RenameDatasetRequest {
+    /// New full dataset name; must stay within the same pool
+    pub target: String,
+    /// Force unmount of busy filesystems before renaming (zfs rename -f)
+    #[serde(default)]
+    pub force_unmount: bool,
+}
+
+// Distinguish an explicit null (Some(None)) from an absent field (None, via serde default)
+fn explicit_null<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<u64>>, D::Error> {
+    Option::<u64>::deserialize(deserializer).map(Some)
+}
+
+// Space limits in bytes; an omitted field is left unchanged and null clears the limit
+#[derive(Deserialize, JsonSchema)]
+pub struct QuotaRequest {
+    #[serde(default, deserialize_with = "explicit_null")]
+    #[schemars(with = "Option<u64>")]
+    pub quota_bytes: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "explicit_null")]
+    #[schemars(with = "Option<u64>")]
+    pub reservation_bytes: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "explicit_null")]
+    #[schemars(with = "Option<u64>")]
+    pub refquota_bytes: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "explicit_null")]
+    #[schemars(with = "Option<u64>")]
+    pub refreservation_bytes: Option<Option<u64>>,
+}
+
+#[derive(Serialize)]
+pub struct PropertyValueResponse {
+    pub dataset: String,
+    pub property: String,
+    pub value: String,
+    /// local, inherited, default, received, temporary or none
+    pub source: String,
+    /// Dataset the value is inherited from, when source is inherited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inherited_from: Option<String>,
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct InheritQuery {
+    /// Also clear the property on descendants (zfs inherit -r)
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct LoadKeyRequest {
+    /// Key material, passed to zfs on stdin; omit to load from the dataset's keylocation
+    pub key: Option<String>,
+    /// Also load keys of encryption roots below the dataset (cannot be combined with key)
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+// Keys are zeroized by the handler once zfs has read them
+#[derive(Deserialize, JsonSchema)]
+pub struct ChangeKeyRequest {
+    /// Current key, used to load it first if it is not already loaded
+    pub current_key: Option<String>,
+    /// New key material for keylocation=prompt, passed to zfs on stdin
+    pub new_key: Option<String>,
+    /// passphrase, hex or raw; defaults to the current keyformat
+    pub new_keyformat: Option<String>,
+    /// "prompt" or a file://, https:// or http:// URL; defaults to the current keylocation
+    pub new_keylocation: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnloadKeyRequest {
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Serialize)]
+pub struct KeyStatusResponse {
+    pub dataset: String,
+    /// available or unavailable
+    pub keystatus: String,
+    pub message: String,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UnmountRequest {
+    /// Unmount even if the filesystem is in use (zfs unmount -f)
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SnapdirRequest {
+    /// Show the .zfs directory in directory listings (snapdir=visible) or hide it (snapdir=hidden)
+    pub visible: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ShareRequest {
+    /// "nfs" or "smb"
+    pub protocol: String,
+    /// Share options stored in sharenfs/sharesmb, e.g. "rw=@10.0.0.0/24,no_root_squash"; "on" when omitted
+    pub options: Option<String>,
+}
+
+// Current sharenfs and sharesmb values; "off" means the dataset is not shared over that protocol
+#[derive(Serialize)]
+pub struct ShareResponse {
+    pub dataset: String,
+    pub sharenfs: String,
+    pub sharesmb: String,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct SnapdirResponse {
+    pub dataset: String,
+    pub snapdir: String,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SnapshotDeletePreviewRequest {
+    /// Snapshot names (the part after '@') to consider deleting together
+    pub snapshots: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotDeletePreviewResponse {
+    pub dataset: String,
+    pub snapshots: Vec<String>,
+    pub reclaimable_bytes: u64,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct DatasetResponse {
+    pub datasets: Vec<String>,
+    /// Number of datasets before pagination
+    pub total: usize,
+    pub has_more: bool,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct DatasetNode {
+    pub name: String,
+    /// filesystem or volume
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub used: u64,
+    pub available: u64,
+    pub referenced: u64,
+    /// None for volumes and for filesystems with mountpoint=none or legacy
+    pub mountpoint: Option<String>,
+    pub children: Vec<DatasetNode>,
+}
+
+#[derive(Serialize)]
+pub struct DatasetTreeResponse {
+    pub tree: DatasetNode,
+    pub status: String,
+}
+
+// Listing pages share these parameters; sort is "name" (default) or "creation", order "asc" or "desc"
+#[derive(Deserialize)]
+pub struct DatasetListQuery {
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BatchPropertiesRequest {
+    pub datasets: Vec<String>,
+    /// Property names to read, e.g. ["used", "compression"]
+    pub fields: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct BatchPropertiesResponse {
+    pub datasets: HashMap<String, HashMap<String, String>>,
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct MountpointQuery {
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct MountpointLookupResponse {
+    pub dataset: String,
+    pub mountpoint: String,
+    pub status: String,
+}
+
+// Snapshots matching `prefix` are deleted unless kept by keep_last or keep_newer_than_secs;
+// at least one of the two must be given. With `recursive` the policy applies per dataset.
+#[derive(Deserialize, JsonSchema)]
+pub struct PruneSnapshotsRequest {
+    /// Keep this many of the newest matching snapshots
+    pub keep_last: Option<u32>,
+    /// Keep matching snapshots created within this many seconds
+    pub keep_newer_than_secs: Option<u64>,
+    /// Only consider snapshots whose name starts with this, e.g. "auto-daily-"
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub recursive: bool,
+    /// Report what would be deleted without deleting anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize)]
+pub struct PruneSnapshotsResponse {
+    pub dataset: String,
+    pub dry_run: bool,
+    /// One entry per selected snapshot: deleted, would_delete or error
+    pub results: Vec<BatchSnapshotResult>,
+    pub status: String,
+}
+
+// Request/Response structures for volumes
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateVolumeRequest {
+    pub name: String,
+    /// Volume size (volsize); must be a multiple of block_size
+    pub size_bytes: u64,
+    /// volblocksize, a power of two between 512 and 16M; fixed once the volume exists
+    pub block_size: Option<u64>,
+    /// Thin-provision the volume with no refreservation (zfs create -s)
+    #[serde(default)]
+    pub sparse: bool,
+    pub properties: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ResizeVolumeRequest {
+    /// New volsize; may not drop below the data already written to the volume
+    pub size_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct VolumeResponse {
+    pub name: String,
+    /// Block device for the volume, e.g. /dev/zvol/tank/vm1
+    pub device: String,
+    pub size_bytes: u64,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct OriginResponse {
+    pub dataset: String,
+    /// Snapshot the dataset was cloned from; None when it is not a clone
+    pub origin: Option<String>,
+    pub status: String,
+}
+
+// Where a dataset's space went, in bytes. The usedby* fields add up to `used`; snapshots have no
+// such breakdown and report them as null.
+#[derive(Serialize)]
+pub struct SpaceUsage {
+    pub used: u64,
+    pub available: Option<u64>,
+    pub usedbydataset: Option<u64>,
+    pub usedbysnapshots: Option<u64>,
+    pub usedbychildren: Option<u64>,
+    pub usedbyrefreservation: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct SpaceUsageResponse {
+    pub dataset: String,
+    pub space: SpaceUsage,
+    pub status: String,
+}
+
+// Request/Response structures for pools
+#[derive(Deserialize)]
+pub struct ImportableQuery {
+    pub refresh: Option<bool>,
+    pub dir: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ImportablePool {
+    pub name: String,
+    pub id: String,
+    pub state: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportablePoolsResponse {
+    pub pools: Vec<ImportablePool>,
+    pub scanned_at: u64,
+    pub cached: bool,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct PoolFeaturesResponse {
+    pub pool: String,
+    /// Feature name (without the feature@ prefix) to disabled, enabled or active
+    pub features: BTreeMap<String, String>,
+    /// Some supported feature is still disabled; the pool's compatibility property may keep it so
+    pub upgrade_available: bool,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct PoolUpgradeResponse {
+    pub pool: String,
+    pub newly_enabled: Vec<String>,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct ReguidResponse {
+    pub pool: String,
+    pub old_guid: String,
+    pub new_guid: String,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct PoolExpandResponse {
+    pub pool: String,
+    /// Pool size in bytes before and after expanding
+    pub old_size: u64,
+    pub new_size: u64,
+    pub expanded_by: u64,
+    /// Devices that were onlined with expansion
+    pub devices: Vec<String>,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ImportPoolRequest {
+    /// Pool name or numeric id, as listed by GET /pools/importable
+    pub pool: String,
+    /// Import under a different name
+    pub new_name: Option<String>,
+    /// Directory to search for devices (zpool import -d)
+    pub dir: Option<String>,
+    /// Import read-only without mounting any datasets; the agent then refuses every change to the
+    /// pool with a read_only error (409)
+    #[serde(default)]
+    pub readonly: bool,
+    /// Mount datasets below this directory instead of their mountpoints (zpool import -R)
+    pub altroot: Option<String>,
+}
+
+// Pool properties are reported with "-" (unset) mapped to None
+#[derive(Serialize, Default)]
+pub struct PoolProperties {
+    pub autoexpand: Option<bool>,
+    pub autoreplace: Option<bool>,
+    pub autotrim: Option<bool>,
+    pub comment: Option<String>,
+    pub cachefile: Option<String>,
+    pub failmode: Option<String>,
+    pub ashift: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct PoolPropertiesResponse {
+    pub pool: String,
+    pub properties: PoolProperties,
+    pub status: String,
+}
+
+// One node of the pool tree: a group (mirror, raidz, logs, ...) or a leaf device
+#[derive(Serialize, Clone)]
+pub struct VdevInfo {
+    pub name: String,
+    /// disk, file, mirror, raidz1, draid2, replacing, spare, or a class header such as logs or cache
+    pub vdev_type: String,
+    /// Device path for leaf vdevs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    pub read_errors: u64,
+    pub write_errors: u64,
+    pub checksum_errors: u64,
+    pub children: Vec<VdevInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct PoolStatusQuery {
+    /// Skip the status cache and always run zpool status
+    pub fresh: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct PoolStatusResponse {
+    pub name: String,
+    pub health: String,
+    /// Number of top-level data vdevs
+    pub vdevs: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topology: Option<Vec<VdevInfo>>,
+    /// While a checkpoint exists, zpool refuses remove, attach, detach, split and reguid
+    pub has_checkpoint: bool,
+    /// Served from the short-lived status cache rather than a fresh zpool status
+    pub cached: bool,
+    /// How old the reported status is, in milliseconds
+    pub age_ms: u64,
+    pub status: String,
+}
+
+// Parsed "scan:" section of zpool status; function is scrub, resilver or none
+pub struct ScanStatus {
+    pub function: String,
+    /// none, scanning, paused, canceled or finished
+    pub state: String,
+    pub percent_done: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct ScrubStatusResponse {
+    pub pool: String,
+    pub function: String,
+    pub state: String,
+    pub percent_done: Option<f64>,
+    pub is_resilvering: bool,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct ResilverStatusResponse {
+    pub pool: String,
+    /// "none" unless the last or current scan is a resilver
+    pub state: String,
+    pub percent_done: Option<f64>,
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct PoolHistoryQuery {
+    /// Include internally logged events (zpool history -i)
+    pub internal: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct PoolHistoryEntry {
+    pub time: String,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PoolHistoryResponse {
+    pub pool: String,
+    /// Newest first
+    pub entries: Vec<PoolHistoryEntry>,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SetPropertyRequest {
+    pub property: String,
+    pub value: String,
+}
+
+// PUT /datasets/{path}/properties accepts either one property or a map of several
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum SetDatasetPropertiesRequest {
+    Single(SetPropertyRequest),
+    Multiple { properties: HashMap<String, String> },
+}
+
+#[derive(Serialize)]
+pub struct SetPropertiesResponse {
+    pub dataset: String,
+    pub applied: Vec<String>,
+    /// Only non-empty when zfs applied part of the request
+    pub failed: Vec<String>,
+    pub message: String,
+    /// success, or partial when some properties were applied before zfs failed
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ReplaceVdevRequest {
+    /// Device currently in the pool, as shown by zpool status
+    pub old_device: String,
+    /// Replacement device path
+    pub new_device: String,
+}
+
+#[derive(Serialize)]
+pub struct ReplaceVdevResponse {
+    pub pool: String,
+    pub old_device: String,
+    pub new_device: String,
+    pub resilver_started: bool,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct AttachVdevRequest {
+    /// Device already in the pool to mirror
+    pub existing_device: String,
+    pub new_device: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct DetachVdevRequest {
+    pub device: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ClearPoolRequest {
+    /// Clear errors on this device only; the whole pool is cleared when omitted
+    pub device: Option<String>,
+}
+
+// Detach one side of every mirror into a new, exported pool (zpool split)
+#[derive(Deserialize, JsonSchema)]
+pub struct SplitPoolRequest {
+    pub new_pool: String,
+    /// Devices to move into the new pool, one per mirror; by default the last device of each mirror
+    pub devices: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct OfflineVdevRequest {
+    /// Offline only until the next reboot (zpool offline -t)
+    #[serde(default)]
+    pub temporary: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct OnlineVdevRequest {
+    /// Grow the device to use all available space, e.g. after replacing with a larger disk (zpool online -e)
+    #[serde(default)]
+    pub expand: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct StartTrimRequest {
+    /// Limit the TRIM rate in bytes per second per device (zpool trim -r)
+    pub rate: Option<u64>,
+    /// Use secure TRIM where the device supports it (zpool trim -d)
+    #[serde(default)]
+    pub secure: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct InitializeRequest {
+    /// Limit to a single device; all devices when omitted
+    pub device: Option<String>,
+}
+
+// Progress of a per-device activity such as TRIM; state is none, active, suspended, complete or unsupported
+#[derive(Serialize)]
+pub struct VdevProgress {
+    pub device: String,
+    pub state: String,
+    pub percent_done: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct VdevProgressResponse {
+    pub pool: String,
+    pub state: String,
+    pub percent_done: Option<f64>,
+    pub vdevs: Vec<VdevProgress>,
+    pub status: String,
+}
+
+#[derive(Deserialize)]
+pub struct IostatQuery {
+    /// Sample over this many milliseconds (1-60000) instead of reporting averages since import
+    pub interval_ms: Option<u64>,
+}
+
+// One row of zpool iostat: ops are per second and bandwidth is bytes per second
+#[derive(Serialize)]
+pub struct IostatSample {
+    pub name: String,
+    /// Not reported for leaf devices of some vdev types or class headers
+    pub alloc: Option<u64>,
+    pub free: Option<u64>,
+    pub read_ops: u64,
+    pub write_ops: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+// Without interval_ms the figures are averages since the pool was imported, so current rates
+// need a sampled call (or the client comparing two samples over time)
+#[derive(Serialize)]
+pub struct PoolIostatResponse {
+    pub pool: String,
+    pub interval_ms: Option<u64>,
+    pub total: IostatSample,
+    pub vdevs: Vec<IostatSample>,
+    pub status: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct LabelClearRequest {
+    #[serde(default)]
+    pub force: bool,
+}
+
+// Request body types with a published JSON Schema, keyed by struct name
+pub const SCHEMA_TYPES: &[&str] = &[
+    "CreateSnapshot",
+    "RenameSnapshot",
+    "HoldRequest",
+    "BatchSnapshotRequest",
+    "SendSnapshot",
+    "CreateRedactionBookmark",
+    "CreateBookmark",
+    "CreateDataset",
+    "ReceiveSnapshot",
+    "RenameDatasetRequest",
+    "UnmountRequest",
+    "LoadKeyRequest",
+    "UnloadKeyRequest",
+    "ChangeKeyRequest",
+    "QuotaRequest",
+    "SnapdirRequest",
+    "ShareRequest",
+    "SnapshotDeletePreviewRequest",
+    "PruneSnapshotsRequest",
+    "BatchPropertiesRequest",
+    "SetPropertyRequest",
+    "SetDatasetPropertiesRequest",
+    "ReplaceVdevRequest",
+    "AttachVdevRequest",
+    "DetachVdevRequest",
+    "ClearPoolRequest",
+    "SplitPoolRequest",
+    "OfflineVdevRequest",
+    "OnlineVdevRequest",
+    "StartTrimRequest",
+    "InitializeRequest",
+    "LabelClearRequest",
+    "ImportPoolRequest",
+    "CreateVolumeRequest",
+    "ResizeVolumeRequest",
+];
+
+pub fn request_schema(type_name: &str) -> Option<schemars::schema::RootSchema> {
+    match type_name {
+        "CreateSnapshot" => Some(schema_for!(CreateSnapshot)),
+        "RenameSnapshot" => Some(schema_for!(RenameSnapshot)),
+        "HoldRequest" => Some(schema_for!(HoldRequest)),
+        "BatchSnapshotRequest" => Some(schema_for!(BatchSnapshotRequest)),
+        "SendSnapshot" => Some(schema_for!(SendSnapshot)),
+        "CreateRedactionBookmark" => Some(schema_for!(CreateRedactionBookmark)),
+        "CreateBookmark" => Some(schema_for!(CreateBookmark)),
+        "CreateDataset" => Some(schema_for!(CreateDataset)),
+        "ReceiveSnapshot" => Some(schema_for!(ReceiveSnapshot)),
+        "RenameDatasetRequest" => Some(schema_for!(RenameDatasetRequest)),
+        "UnmountRequest" => Some(schema_for!(UnmountRequest)),
+        "LoadKeyRequest" => Some(schema_for!(LoadKeyRequest)),
+        "UnloadKeyRequest" => Some(schema_for!(UnloadKeyRequest)),
+        "ChangeKeyRequest" => Some(schema_for!(ChangeKeyRequest)),
+        "QuotaRequest" => Some(schema_for!(QuotaRequest)),
+        "SnapdirRequest" => Some(schema_for!(SnapdirRequest)),
+        "ShareRequest" => Some(schema_for!(ShareRequest)),
+        "SnapshotDeletePreviewRequest" => Some(schema_for!(SnapshotDeletePreviewRequest)),
+        "PruneSnapshotsRequest" => Some(schema_for!(PruneSnapshotsRequest)),
+        "BatchPropertiesRequest" => Some(schema_for!(BatchPropertiesRequest)),
+        "SetPropertyRequest" => Some(schema_for!(SetPropertyRequest)),
+        "SetDatasetPropertiesRequest" => Some(schema_for!(SetDatasetPropertiesRequest)),
+        "ReplaceVdevRequest" => Some(schema_for!(ReplaceVdevRequest)),
+        "AttachVdevRequest" => Some(schema_for!(AttachVdevRequest)),
+        "DetachVdevRequest" => Some(schema_for!(DetachVdevRequest)),
+        "ClearPoolRequest" => Some(schema_for!(ClearPoolRequest)),
+        "SplitPoolRequest" => Some(schema_for!(SplitPoolRequest)),
+        "OfflineVdevRequest" => Some(schema_for!(OfflineVdevRequest)),
+        "OnlineVdevRequest" => Some(schema_for!(OnlineVdevRequest)),
+        "StartTrimRequest" => Some(schema_for!(StartTrimRequest)),
+        "InitializeRequest" => Some(schema_for!(InitializeRequest)),
+        "LabelClearRequest" => Some(schema_for!(LabelClearRequest)),
+        "ImportPoolRequest" => Some(schema_for!(ImportPoolRequest)),
+        "CreateVolumeRequest" => Some(schema_for!(CreateVolumeRequest)),
+        "ResizeVolumeRequest" => Some(schema_for!(ResizeVolumeRequest)),
+        _ => None,
+    }
+}
+
+// Selected counters from /proc/spl/kstat/zfs/arcstats; sizes are bytes
+#[derive(Serialize)]
+pub struct ArcStats {
+    pub size: u64,
+    /// Current target size
+    pub c: u64,
+    pub c_min: u64,
+    pub c_max: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// hits / (hits + misses); None before the first access
+    pub hit_ratio: Option<f64>,
+    pub mru_size: u64,
+    pub mfu_size: u64,
+    pub mru_hits: u64,
+    pub mfu_hits: u64,
+    pub mru_ghost_hits: u64,
+    pub mfu_ghost_hits: u64,
+    /// L2ARC counters, present only when a cache device has been used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l2_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l2_hits: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l2_misses: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct ArcStatsResponse {
+    pub arc: ArcStats,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct PoolSummary {
+    pub name: String,
+    /// ONLINE, DEGRADED, FAULTED, OFFLINE, UNAVAIL or REMOVED
+    pub health: String,
+}
+
+// Readiness: ZFS answered a pool listing. Unhealthy pools are reported but do not fail readiness.
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub pool_count: usize,
+    /// Pools whose health is anything but ONLINE
+    pub unhealthy_pools: Vec<PoolSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub status: String,
+}
+
+// One record from the ZFS event log (zpool events), as pushed to GET /events subscribers
+#[derive(Serialize)]
+pub struct ZfsEvent {
+    /// e.g. sysevent.fs.zfs.scrub_finish or ereport.fs.zfs.checksum
+    pub class: String,
+    pub pool: Option<String>,
+    /// Device path of the vdev the event concerns, if any
+    pub vdev: Option<String>,
+    /// Seconds since the epoch
+    pub timestamp: u64,
+    /// Event id; ids increase, so clients can drop events they have already seen
+    pub eid: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    /// Defaults to 100, at most 1000
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct AuditResponse {
+    /// Newest first
+    pub entries: Vec<AuditEntry>,
+    pub status: String,
+}
+
+#[derive(Serialize)]
+pub struct SchemaListResponse {
+    pub types: Vec<String>,
+    pub status: String,
+}
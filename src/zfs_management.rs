@@ -0,0 +1,3130 @@
+use libzetta::zfs::{
+    DelegatingZfsEngine, 
+    ZfsEngine,
+    CreateDatasetRequest, 
+    DatasetKind
+};
+use libzetta::zpool::{ZpoolEngine, ZpoolOpen3};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::process::{Command, Stdio};
+use std::io::{Read, Write};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt};
+
+use crate::error::ZfsError;
+use crate::models::*;
+
+const ARCSTATS_PATH: &str = "/proc/spl/kstat/zfs/arcstats";
+
+// Run synchronous zfs/zpool work on tokio's blocking pool, so async workers never wait on a child
+// process or the disk. `f` owns everything it uses, since it runs on another thread.
+pub async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+fn owned_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn command_result(program: &str, args: &[&str], output: std::process::Output) -> Result<String, ZfsError> {
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(ZfsError::from_stderr(format!("{} {} failed: {}", program, args.first().unwrap_or(&""), stderr.trim())))
+    }
+}
+
+// Run a zfs/zpool command with an argument array (no shell involved) and return stdout
+pub async fn run_command(program: &str, args: &[&str]) -> Result<String, ZfsError> {
+    let (program, args) = (program.to_string(), owned_args(args));
+    blocking(move || {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_command_blocking(&program, &args)
+    })
+    .await
+}
+
+// run_command for code that already runs off the async workers, e.g. under spawn_blocking
+pub fn run_command_blocking(program: &str, args: &[&str]) -> Result<String, ZfsError> {
+    let output = Command::new(program).args(args).output()?;
+    command_result(program, args, output)
+}
+
+// Like run_command, but feed `input` on stdin; used for key material that must not appear in argv
+pub async fn run_command_with_stdin(program: &str, args: &[&str], input: &[u8]) -> Result<String, ZfsError> {
+    let (program, args, input) = (program.to_string(), owned_args(args), input.to_vec());
+    blocking(move || {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let mut child = Command::new(&program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&input)?;
+        }
+        let output = child.wait_with_output()?;
+        command_result(&program, &args, output)
+    })
+    .await
+}
+
+// Dataset names are passed as CLI arguments, so only allow characters ZFS itself accepts
+pub fn validate_dataset_name(name: &str) -> Result<(), ZfsError> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '/'));
+
+    if name.is_empty()
+        || name.len() > 255
+        || name.starts_with('-')
+        || name.starts_with('/')
+        || name.ends_with('/')
+        || name.contains("//")
+        || !valid_chars
+    {
+        return Err(ZfsError::InvalidArgument(format!("Invalid dataset name '{}'", name)));
+    }
+    Ok(())
+}
+
+// Snapshot names are the part after '@' and follow the same character rules as dataset components
+pub fn validate_snapshot_name(name: &str) -> Result<(), ZfsError> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+
+    if name.is_empty() || name.len() > 255 || name.starts_with('-') || !valid_chars {
+        return Err(ZfsError::InvalidArgument(format!("Invalid snapshot name '{}'", name)));
+    }
+    Ok(())
+}
+
+// Validate a hold request and return the full snapshot path it applies to
+fn hold_target(dataset: &str, snapshot_name: &str, tag: &str) -> Result<String, ZfsError> {
+    validate_dataset_name(dataset)?;
+    validate_snapshot_name(snapshot_name)?;
+    validate_snapshot_name(tag).map_err(|_| ZfsError::InvalidArgument(format!("Invalid hold tag '{}'", tag)))?;
+    Ok(format!("{}@{}", dataset, snapshot_name))
+}
+
+// Pool properties the API reads and may set; anything else is rejected before reaching zpool
+const POOL_PROPERTIES: &[&str] = &["autoexpand", "autoreplace", "autotrim", "comment", "cachefile", "failmode", "ashift"];
+
+// Lines of the config section of `zpool status`, without the column header
+fn status_config_lines(status: &str) -> impl Iterator<Item = &str> {
+    status
+        .lines()
+        .skip_while(|line| line.trim() != "config:")
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with("errors:"))
+        .filter(|line| !line.trim().is_empty() && line.split_whitespace().next() != Some("NAME"))
+}
+
+// Parse the config section of `zpool status -P` into (indent, name) pairs, pool line first
+fn parse_vdev_tree(status: &str) -> Vec<(usize, String)> {
+    status_config_lines(status)
+        .map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            (indent, line.split_whitespace().next().unwrap_or("").to_string())
+        })
+        .collect()
+}
+
+// Group vdev names that carry a numeric suffix in zpool status, e.g. mirror-0 or raidz2-1
+const VDEV_GROUP_TYPES: &[&str] = &["mirror", "raidz1", "raidz2", "raidz3", "raidz", "draid", "replacing", "spare"];
+
+// Build the nested vdev tree from `zpool status -P -p` config lines; the pool itself is the first root
+fn build_vdev_tree(lines: &[&str]) -> Vec<VdevInfo> {
+    let indent_of = |line: &str| line.len() - line.trim_start().len();
+    let mut nodes = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let indent = indent_of(lines[i]);
+        let end = lines[i + 1..]
+            .iter()
+            .position(|line| indent_of(line) <= indent)
+            .map_or(lines.len(), |p| i + 1 + p);
+
+        let fields: Vec<&str> = lines[i].split_whitespace().collect();
+        let name = fields[0].to_string();
+        let count = |n: usize| fields.get(n).and_then(|v| v.parse().ok()).unwrap_or(0);
+        let vdev_type = if name.starts_with('/') {
+            if name.starts_with("/dev/") { "disk" } else { "file" }.to_string()
+        } else {
+            VDEV_GROUP_TYPES
+                .iter()
+                .find(|t| name.starts_with(*t))
+                .map_or_else(|| name.clone(), |t| name.split('-').next().unwrap_or(t).to_string())
+        };
+
+        nodes.push(VdevInfo {
+            path: name.starts_with('/').then(|| name.clone()),
+            state: fields.get(1).map(|s| s.to_string()),
+            read_errors: count(2),
+            write_errors: count(3),
+            checksum_errors: count(4),
+            children: build_vdev_tree(&lines[i + 1..end]),
+            vdev_type,
+            name,
+        });
+        i = end;
+    }
+    nodes
+}
+
+// Read per-device annotations such as "(45% trimmed, started at ...)" from `zpool status -t` or `-i`.
+// `verb` is the word zpool uses for the activity ("trimmed" or "initialized").
+fn parse_vdev_progress(status: &str, verb: &str) -> Vec<VdevProgress> {
+    status_config_lines(status)
+        .filter_map(|line| {
+            let device = line.split_whitespace().next()?.to_string();
+            let annotation = &line[line.rfind('(')? + 1..line.rfind(')')?];
+            let (state, percent_done) = if annotation.contains("unsupported") {
+                ("unsupported", None)
+            } else if annotation.starts_with("un") {
+                ("none", None)
+            } else if annotation.contains(verb) {
+                let percent = annotation.split('%').next()?.trim().parse::<f64>().ok();
+                let state = if annotation.contains("suspended") {
+                    "suspended"
+                } else if annotation.contains("completed") {
+                    "complete"
+                } else {
+                    "active"
+                };
+                (state, percent)
+            } else {
+                return None;
+            };
+            Some(VdevProgress { device, state: state.to_string(), percent_done })
+        })
+        .collect()
+}
+
+// Parse the "scan:" section of `zpool status`, which may continue over the following indented lines
+fn parse_scan_status(status: &str) -> ScanStatus {
+    let mut lines = status.lines().skip_while(|line| !line.trim_start().starts_with("scan:"));
+    let mut text = lines
+        .next()
+        .and_then(|line| line.trim().strip_prefix("scan:"))
+        .unwrap_or("none requested")
+        .trim()
+        .to_string();
+    for line in lines.take_while(|line| !line.trim_start().contains(": ") && !line.trim().ends_with(':')) {
+        text.push(' ');
+        text.push_str(line.trim());
+    }
+
+    let function = if text.starts_with("scrub") {
+        "scrub"
+    } else if text.starts_with("resilver") {
+        "resilver"
+    } else {
+        "none"
+    };
+    let state = if function == "none" {
+        "none"
+    } else if text.contains("in progress") {
+        "scanning"
+    } else if text.contains("paused") {
+        "paused"
+    } else if text.contains("canceled") {
+        "canceled"
+    } else {
+        "finished"
+    };
+    let percent_done = match state {
+        "finished" => Some(100.0),
+        "none" => None,
+        _ => scan_percent(&text),
+    };
+    ScanStatus { function: function.to_string(), state: state.to_string(), percent_done }
+}
+
+// Percent complete from the "12.34% done" part of a scan description
+fn scan_percent(text: &str) -> Option<f64> {
+    let end = text.find("% done")?;
+    text[..end].rsplit(|c: char| c.is_whitespace() || c == ',').next()?.parse().ok()
+}
+
+// Summarize per-device progress into an overall state and average completion
+fn summarize_vdev_progress(vdevs: &[VdevProgress]) -> (String, Option<f64>) {
+    let has = |state: &str| vdevs.iter().any(|v| v.state == state);
+    let state = if has("active") {
+        "active"
+    } else if has("suspended") {
+        "suspended"
+    } else if has("complete") && !has("none") {
+        "complete"
+    } else {
+        "none"
+    };
+    let percents: Vec<f64> = vdevs.iter().filter_map(|v| v.percent_done).collect();
+    let percent_done = if percents.is_empty() {
+        None
+    } else {
+        Some(percents.iter().sum::<f64>() / percents.len() as f64)
+    };
+    (state.to_string(), percent_done)
+}
+
+// Parsable value of a single pool property, e.g. size or guid
+async fn pool_property(pool: &str, property: &str) -> Result<String, ZfsError> {
+    Ok(run_command("zpool", &["get", "-H", "-p", "-o", "value", property, pool]).await?.trim().to_string())
+}
+
+// Whether a vdev tree entry refers to the device as given by the caller (full path or short name)
+fn is_same_device(entry: &str, device: &str) -> bool {
+    entry == device || entry.ends_with(&format!("/{}", device.trim_start_matches("/dev/")))
+}
+
+// Build the `zfs receive` arguments shared by the file and stream sources
+fn receive_args(target: &str, force: bool, discard_first_element: bool, use_last_element: bool, resumable: bool) -> Result<Vec<&str>, ZfsError> {
+    validate_dataset_name(target)?;
+    if discard_first_element && use_last_element {
+        return Err(ZfsError::InvalidArgument("discard_first_element (-d) and use_last_element (-e) cannot be combined".to_string()));
+    }
+
+    let mut args = vec!["receive"];
+    if force {
+        args.push("-F");
+    }
+    if discard_first_element {
+        args.push("-d");
+    }
+    if use_last_element {
+        args.push("-e");
+    }
+    if resumable {
+        args.push("-s");
+    }
+    args.push(target);
+    Ok(args)
+}
+
+const SEND_CHUNK_SIZE: usize = 128 * 1024;
+
+// How long to pause so that `copied` bytes over `elapsed` stays at or under `rate_limit` bytes/s
+pub fn throttle_delay(copied: u64, elapsed: Duration, rate_limit: Option<u64>) -> Option<Duration> {
+    let rate = rate_limit.filter(|&rate| rate > 0)?;
+    let target = Duration::from_secs_f64(copied as f64 / rate as f64);
+    target.checked_sub(elapsed).filter(|delay| !delay.is_zero())
+}
+
+// Copy a send stream to its destination, throttled to `rate_limit`; returns bytes copied and time taken
+fn copy_throttled(reader: &mut impl Read, writer: &mut impl Write, rate_limit: Option<u64>) -> std::io::Result<(u64, Duration)> {
+    let started = Instant::now();
+    let mut buf = vec![0u8; SEND_CHUNK_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+        if let Some(delay) = throttle_delay(copied, started.elapsed(), rate_limit) {
+            std::thread::sleep(delay);
+        }
+    }
+    writer.flush()?;
+    Ok((copied, started.elapsed()))
+}
+
+// Like tokio::io::copy, but fail with a timeout when no data moves for `stall_timeout`
+async fn copy_with_stall_timeout<R, W>(reader: &mut R, writer: &mut W, stall_timeout: Duration) -> Result<u64, ZfsError>
+where
+    R: AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let stalled = || ZfsError::Timeout(format!("Stream made no progress for {}s and was aborted", stall_timeout.as_secs()));
+    let mut buf = vec![0u8; SEND_CHUNK_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let n = tokio::time::timeout(stall_timeout, reader.read(&mut buf)).await.map_err(|_| stalled())??;
+        if n == 0 {
+            break;
+        }
+        tokio::time::timeout(stall_timeout, writer.write_all(&buf[..n])).await.map_err(|_| stalled())??;
+        copied += n as u64;
+    }
+    Ok(copied)
+}
+
+// A resumed send takes its snapshot and stream flags from the token, so zfs rejects any others
+fn resume_send_args(token: &str, request: &SendSnapshot) -> Result<Vec<String>, ZfsError> {
+    let valid = !token.is_empty() && token.len() <= 8192 && token.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+    if !valid || token.starts_with('-') {
+        return Err(ZfsError::InvalidArgument("Invalid resume token".to_string()));
+    }
+    let conflicting = request.from_snapshot.is_some()
+        || request.include_intermediate
+        || request.properties
+        || request.redact_bookmark.is_some()
+        || request.raw
+        || request.compressed
+        || request.recursive
+        || request.embed_data.is_some()
+        || request.large_blocks.is_some();
+    if conflicting {
+        return Err(ZfsError::InvalidArgument(
+            "resume_token cannot be combined with other send options; the token already records them".to_string(),
+        ));
+    }
+    Ok(vec!["send".to_string(), "-t".to_string(), token.to_string()])
+}
+
+fn share_property(protocol: &str) -> Result<&'static str, ZfsError> {
+    match protocol {
+        "nfs" => Ok("sharenfs"),
+        "smb" => Ok("sharesmb"),
+        _ => Err(ZfsError::InvalidArgument(format!("Invalid share protocol '{}': must be nfs or smb", protocol))),
+    }
+}
+
+// Share options end up in exportfs/net usershare command lines, so allow only the characters
+// option lists use (no whitespace, quotes or shell metacharacters)
+fn validate_share_options(options: &str) -> Result<(), ZfsError> {
+    let valid = !options.is_empty()
+        && options.len() <= 1024
+        && !options.starts_with('-')
+        && options
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '=' | ',' | ':' | '/' | '.' | '@' | '_' | '-' | '*' | '+'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ZfsError::InvalidArgument(format!("Invalid share options '{}'", options)))
+    }
+}
+
+// Resolve a client-supplied stream file path to one inside `dir`, the send directory. The parent is
+// canonicalized so symlinked directories cannot lead outside it.
+async fn stream_file_path(dir: &std::path::Path, file: &str) -> Result<PathBuf, ZfsError> {
+    let path = std::path::Path::new(file);
+    let file_name = match path.file_name() {
+        Some(name) if path.is_absolute() && !path.components().any(|c| c == std::path::Component::ParentDir) => name,
+        _ => return Err(ZfsError::InvalidArgument(format!("Stream file '{}' must be an absolute path to a file", file))),
+    };
+    let send_dir = send_dir_root(dir).await?;
+    let parent = match path.parent() {
+        Some(parent) => tokio::fs::canonicalize(parent).await.ok(),
+        None => None,
+    }
+    .ok_or_else(|| ZfsError::NotFound(format!("Directory of stream file '{}' does not exist", file)))?;
+    if !parent.starts_with(&send_dir) {
+        return Err(outside_send_dir(file, &send_dir));
+    }
+    Ok(parent.join(file_name))
+}
+
+async fn send_dir_root(dir: &std::path::Path) -> Result<PathBuf, ZfsError> {
+    tokio::fs::canonicalize(dir)
+        .await
+        .map_err(|e| ZfsError::Other(format!("Send directory '{}' is not usable: {}", dir.display(), e)))
+}
+
+fn outside_send_dir(file: &str, send_dir: &std::path::Path) -> ZfsError {
+    ZfsError::PermissionDenied(format!("Stream file '{}' must be inside the send directory '{}'", file, send_dir.display()))
+}
+
+// The input file must be inside the send directory, and is resolved in full so a symlink cannot
+// point elsewhere. It is only ever opened here and handed to zfs as stdin, never passed to a shell.
+async fn open_receive_input(dir: &std::path::Path, file: &str) -> Result<tokio::fs::File, ZfsError> {
+    let not_found = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::NotFound => ZfsError::NotFound(format!("Input file '{}' does not exist", file)),
+        _ => ZfsError::from(e),
+    };
+    let path = stream_file_path(dir, file).await?;
+    let resolved = tokio::fs::canonicalize(&path).await.map_err(not_found)?;
+    let send_dir = send_dir_root(dir).await?;
+    if !resolved.starts_with(&send_dir) {
+        return Err(outside_send_dir(file, &send_dir));
+    }
+    tokio::fs::File::open(&resolved).await.map_err(not_found)
+}
+
+// One line of `zfs diff -H` output; lines of unknown change types are skipped
+fn parse_diff_line(line: &str) -> Option<DiffEntry> {
+    let mut fields = line.split('\t');
+    let change_type = match fields.next()? {
+        "M" => "modified",
+        "+" => "created",
+        "-" => "removed",
+        "R" => "renamed",
+        _ => return None,
+    };
+    Some(DiffEntry {
+        change_type: change_type.to_string(),
+        path: fields.next()?.to_string(),
+        new_path: fields.next().map(|p| p.to_string()),
+    })
+}
+
+// A running `zfs diff`. Dropping it kills zfs, e.g. when the client of a streamed diff goes away.
+pub struct DiffStream {
+    dataset: String,
+    lines: tokio::io::Lines<tokio::io::BufReader<tokio::process::ChildStdout>>,
+    stderr: Option<tokio::task::JoinHandle<String>>,
+    child: tokio::process::Child,
+}
+
+impl DiffStream {
+    // The next change, or None once zfs diff has finished successfully
+    pub async fn next_entry(&mut self) -> Result<Option<DiffEntry>, ZfsError> {
+        while let Some(line) = self.lines.next_line().await? {
+            if let Some(entry) = parse_diff_line(&line) {
+                return Ok(Some(entry));
+            }
+        }
+        if self.child.wait().await?.success() {
+            return Ok(None);
+        }
+        let stderr = match self.stderr.take() {
+            Some(stderr) => stderr.await.unwrap_or_default(),
+            None => String::new(),
+        };
+        if stderr.contains("not mounted") || stderr.contains("must be mounted") {
+            return Err(ZfsError::InvalidArgument(format!(
+                "Dataset '{}' must be mounted to compute a diff",
+                self.dataset
+            )));
+        }
+        Err(ZfsError::from_stderr(format!("zfs diff failed: {}", stderr.trim())))
+    }
+}
+
+// Build an event from the fields of one `zpool events -v` record ("key = value" lines).
+// Strings are quoted and numbers are hex; `time` holds seconds and nanoseconds.
+fn parse_event(class: &str, fields: &HashMap<String, String>) -> ZfsEvent {
+    let string = |key: &str| fields.get(key).map(|v| v.trim_matches('"').to_string());
+    let number = |value: &str| match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    };
+    ZfsEvent {
+        class: string("class").unwrap_or_else(|| class.to_string()),
+        pool: string("pool"),
+        vdev: string("vdev_path"),
+        timestamp: fields
+            .get("time")
+            .and_then(|time| time.split_whitespace().next())
+            .and_then(number)
+            .unwrap_or(0),
+        eid: fields.get("eid").and_then(|eid| number(eid)),
+    }
+}
+
+// Native properties are lowercase words; user properties contain a ':' (e.g. com.example:owner)
+pub fn is_valid_property_name(property: &str) -> bool {
+    !property.is_empty()
+        && property.len() <= 256
+        && !property.starts_with('-')
+        && property
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '.' | ':' | '@'))
+}
+
+// Longest property value ZFS accepts (ZAP_MAXVALUELEN)
+const MAX_PROPERTY_VALUE_LEN: usize = 8192;
+
+// Values are passed as one "prop=value" argument; reject anything that could be misread by zfs
+pub fn validate_property_value(property: &str, value: &str) -> Result<(), ZfsError> {
+    let invalid = |reason: &str| Err(ZfsError::InvalidArgument(format!("Invalid value for property '{}': {}", property, reason)));
+    if value.len() > MAX_PROPERTY_VALUE_LEN {
+        return invalid("value is too long");
+    }
+    if value.contains(['\0', '\n', '\r']) {
+        return invalid("value contains a null byte or line break");
+    }
+    if value.starts_with('-') {
+        return invalid("value starts with '-'");
+    }
+    // Only user properties (which contain ':') have free-form values
+    if value.contains('=') && !property.contains(':') {
+        return invalid("'=' is only allowed in user property values");
+    }
+    Ok(())
+}
+
+// Parse a ZFS size value such as "131072", "128K" or "16M" into bytes
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let digits_end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, suffix) = value.split_at(digits_end);
+    let number: u64 = number.parse().ok()?;
+
+    let multiplier: u64 = match suffix.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        "P" | "PB" | "PIB" => 1 << 50,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+const MIN_BLOCK_SIZE: u64 = 512;
+const MAX_BLOCK_SIZE: u64 = 16 << 20;
+
+// recordsize (filesystems) and volblocksize (volumes) must be powers of two between 512 and 16M
+pub fn validate_block_size_properties(properties: &HashMap<String, String>, is_volume: bool) -> Result<(), ZfsError> {
+    for (property, value) in properties {
+        let note = match (property.as_str(), is_volume) {
+            ("recordsize", true) => return Err(ZfsError::InvalidArgument("recordsize applies only to filesystems; use volblocksize for volumes".to_string())),
+            ("volblocksize", false) => return Err(ZfsError::InvalidArgument("volblocksize applies only to volumes; use recordsize for filesystems".to_string())),
+            ("recordsize", false) => "",
+            ("volblocksize", true) => " (volblocksize cannot be changed after the volume is created)",
+            _ => continue,
+        };
+
+        let valid = matches!(parse_size(value), Some(size) if size.is_power_of_two() && (MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&size));
+        if !valid {
+            return Err(ZfsError::InvalidArgument(format!("Invalid {} '{}': must be a power of two between 512 and 16M{}", property, value, note)));
+        }
+    }
+    Ok(())
+}
+
+const ENCRYPTION_ALGORITHMS: &[&str] = &[
+    "on", "aes-128-ccm", "aes-192-ccm", "aes-256-ccm", "aes-128-gcm", "aes-192-gcm", "aes-256-gcm",
+];
+const ENCRYPTION_PROPERTIES: &[&str] = &["encryption", "keyformat", "keylocation", "pbkdf2iters"];
+
+// Check inline key material against its keyformat; error messages never include the key itself
+pub fn validate_key(keyformat: &str, key: &str) -> Result<(), ZfsError> {
+    let valid = match keyformat {
+        "passphrase" => (8..=512).contains(&key.len()) && !key.contains(['\0', '\n', '\r']),
+        "hex" => key.len() == 64 && key.chars().all(|c| c.is_ascii_hexdigit()),
+        "raw" => return Err(ZfsError::InvalidArgument(
+            "Raw keys cannot be sent inline; use a file:// or https:// keylocation".to_string(),
+        )),
+        _ => return Err(ZfsError::InvalidArgument(format!("Invalid keyformat '{}': must be passphrase, hex or raw", keyformat))),
+    };
+    if !valid {
+        let expected = if keyformat == "hex" { "64 hexadecimal characters" } else { "8 to 512 characters on one line" };
+        return Err(ZfsError::InvalidArgument(format!("Invalid {} key: must be {}", keyformat, expected)));
+    }
+    Ok(())
+}
+
+pub fn validate_keylocation(keylocation: &str) -> Result<(), ZfsError> {
+    let valid = keylocation == "prompt"
+        || ["file:///", "https://", "http://"].iter().any(|scheme| keylocation.starts_with(scheme));
+    if !valid || keylocation.contains(['\0', '\n', '\r']) {
+        return Err(ZfsError::InvalidArgument(format!(
+            "Invalid keylocation '{}': must be 'prompt' or a file:///, https:// or http:// URL",
+            keylocation
+        )));
+    }
+    Ok(())
+}
+
+// Turn the encryption fields of a create request into zfs create options, rejecting
+// combinations zfs would refuse. Returns None for unencrypted datasets.
+fn encryption_options(request: &CreateDataset) -> Result<Option<Vec<String>>, ZfsError> {
+    let invalid = |message: &str| Err(ZfsError::InvalidArgument(message.to_string()));
+    if let Some(properties) = &request.properties {
+        if let Some(property) = ENCRYPTION_PROPERTIES.iter().find(|p| properties.contains_key(**p)) {
+            return Err(ZfsError::InvalidArgument(format!(
+                "Set '{}' through the dataset's encryption fields, not properties",
+                property
+            )));
+        }
+    }
+
+    let Some(encryption) = request.encryption.as_deref() else {
+        if request.keyformat.is_some() || request.keylocation.is_some() || request.key.is_some() {
+            return invalid("keyformat, keylocation and key require encryption to be set");
+        }
+        return Ok(None);
+    };
+    if !ENCRYPTION_ALGORITHMS.contains(&encryption) {
+        return Err(ZfsError::InvalidArgument(format!(
+            "Invalid encryption '{}': must be one of {}",
+            encryption,
+            ENCRYPTION_ALGORITHMS.join(", ")
+        )));
+    }
+    let Some(keyformat) = request.keyformat.as_deref() else {
+        return invalid("encryption requires keyformat (passphrase, hex or raw)");
+    };
+    let keylocation = request.keylocation.as_deref().unwrap_or("prompt");
+    validate_keylocation(keylocation)?;
+
+    match (keylocation, request.key.as_deref()) {
+        ("prompt", Some(key)) => validate_key(keyformat, key)?,
+        ("prompt", None) => return invalid("keylocation 'prompt' requires the key to be given in 'key'"),
+        (_, Some(_)) => return invalid("key cannot be given when keylocation points to a file or URL"),
+        (_, None) => {
+            if !["passphrase", "hex", "raw"].contains(&keyformat) {
+                return Err(ZfsError::InvalidArgument(format!("Invalid keyformat '{}': must be passphrase, hex or raw", keyformat)));
+            }
+        }
+    }
+
+    Ok(Some(vec![
+        format!("encryption={}", encryption),
+        format!("keyformat={}", keyformat),
+        format!("keylocation={}", keylocation),
+    ]))
+}
+
+// libzetta cannot feed a key on stdin, so encrypted datasets are created through the zfs CLI
+async fn create_encrypted(request: &CreateDataset, is_volume: bool, encryption: &[String]) -> Result<(), ZfsError> {
+    let mut options: Vec<String> = encryption.to_vec();
+    let mut volsize = None;
+    for (property, value) in request.properties.iter().flatten() {
+        if property == "volsize" && is_volume {
+            volsize = Some(value.as_str());
+        } else {
+            options.push(format!("{}={}", property, value));
+        }
+    }
+
+    let mut args = vec!["create"];
+    if is_volume {
+        let Some(volsize) = volsize else {
+            return Err(ZfsError::InvalidArgument("Encrypted volumes require a volsize property".to_string()));
+        };
+        args.extend(["-V", volsize]);
+    }
+    for option in &options {
+        args.extend(["-o", option.as_str()]);
+    }
+    args.push(&request.name);
+
+    match request.key.as_deref() {
+        Some(key) => run_command_with_stdin("zfs", &args, key.as_bytes()).await?,
+        None => run_command("zfs", &args).await?,
+    };
+    Ok(())
+}
+
+// Reject device paths that could be read as options or smuggle shell metacharacters
+pub fn validate_device_path(device: &str) -> Result<(), ZfsError> {
+    const DANGEROUS_CHARS: &[char] = &[';', '&', '|', '$', '`', '<', '>', '(', ')', '\'', '"', '\\', '*', '?', ' ', '\n', '\r', '\0'];
+
+    if device.is_empty() {
+        return Err(ZfsError::InvalidArgument("Device path cannot be empty".to_string()));
+    }
+    if device.starts_with('-') {
+        return Err(ZfsError::InvalidArgument(format!("Invalid device path '{}': must not start with '-'", device)));
+    }
+    if device.contains("..") || device.chars().any(|c| DANGEROUS_CHARS.contains(&c)) {
+        return Err(ZfsError::InvalidArgument(format!("Invalid device path '{}': contains forbidden characters", device)));
+    }
+    Ok(())
+}
+
+// SSH destinations are passed as a single argument; allow user@host, IPv6 brackets and ports only
+pub fn validate_remote_host(host: &str) -> Result<(), ZfsError> {
+    let valid_chars = host
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '@' | ':' | '[' | ']'));
+
+    if host.is_empty() || host.starts_with('-') || !valid_chars {
+        return Err(ZfsError::InvalidArgument(format!("Invalid remote host '{}'", host)));
+    }
+    Ok(())
+}
+
+// Quote a value for the remote shell that ssh runs the command through
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// Read a boolean setting from the environment, falling back to `default` when unset or unrecognised
+pub fn env_flag(name: &str, default: bool) -> bool {
+    match std::env::var(name).map(|v| v.to_ascii_lowercase()) {
+        Ok(v) if matches!(v.as_str(), "1" | "true" | "yes" | "on") => true,
+        Ok(v) if matches!(v.as_str(), "0" | "false" | "no" | "off") => false,
+        _ => default,
+    }
+}
+
+// Server-wide stream feature defaults. Both are off so streams stay receivable by older ZFS
+// releases unless the operator or the request opts in.
+#[derive(Clone, Copy)]
+struct SendDefaults {
+    embed_data: bool,
+    large_blocks: bool,
+}
+
+impl SendDefaults {
+    fn from_env() -> Self {
+        SendDefaults {
+            embed_data: env_flag("ZFS_AGENT_SEND_EMBED_DATA", false),
+            large_blocks: env_flag("ZFS_AGENT_SEND_LARGE_BLOCKS", false),
+        }
+    }
+}
+
+// How long a long-running child (e.g. zfs receive) may go without progress before it is killed.
+// Set with ZFS_AGENT_COMMAND_TIMEOUT_SECS; transfers that keep moving data are never cut off.
+const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+
+fn command_timeout_from_env() -> Duration {
+    std::env::var("ZFS_AGENT_COMMAND_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|&secs: &u64| secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_COMMAND_TIMEOUT)
+}
+
+// Stream files written by send_snapshot_to_file and read by receive_snapshot_from_file must live
+// below this directory; set with ZFS_AGENT_SEND_DIR
+const DEFAULT_SEND_DIR: &str = "/var/lib/zfs-webmanager-agent/sends";
+
+fn send_dir_from_env() -> PathBuf {
+    std::env::var("ZFS_AGENT_SEND_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SEND_DIR))
+}
+
+// How long an importable-pool scan is reused before devices are scanned again
+const IMPORTABLE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+struct ImportableScan {
+    dir: Option<String>,
+    taken: Instant,
+    scanned_at: u64,
+    pools: Vec<ImportablePool>,
+}
+
+// How long a pool status read is reused; set with ZFS_AGENT_POOL_STATUS_TTL_MS (0 disables caching)
+const DEFAULT_POOL_STATUS_TTL: Duration = Duration::from_secs(2);
+
+fn pool_status_ttl_from_env() -> Duration {
+    std::env::var("ZFS_AGENT_POOL_STATUS_TTL_MS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_POOL_STATUS_TTL)
+}
+
+// Health, number of data vdevs, full vdev tree and whether a checkpoint exists
+pub type PoolStatus = (String, u32, Vec<VdevInfo>, bool);
+
+struct CachedPoolStatus {
+    taken: Instant,
+    status: PoolStatus,
+}
+
+// ZFS wrapper to make it easier to share between routes
+#[derive(Clone)]
+pub struct ZfsManager {
+    // None only in unit tests, which run without /dev/zfs; reached through with_engine
+    engine: Option<Arc<DelegatingZfsEngine>>,
+    zpool_engine: Arc<ZpoolOpen3>,
+    importable_cache: Arc<Mutex<Option<ImportableScan>>>,
+    // Keyed by pool name; dropped for a pool by every mutating pool operation
+    status_cache: Arc<Mutex<HashMap<String, CachedPoolStatus>>>,
+    status_cache_ttl: Duration,
+    send_defaults: SendDefaults,
+    send_dir: PathBuf,
+    command_timeout: Duration,
+}
+
+impl ZfsManager {
+    pub fn new() -> Result<Self, ZfsError> {
+        Ok(ZfsManager {
+            engine: Some(Arc::new(DelegatingZfsEngine::new()?)),
+            zpool_engine: Arc::new(ZpoolOpen3::default()),
+            importable_cache: Arc::new(Mutex::new(None)),
+            status_cache: Arc::new(Mutex::new(HashMap::new())),
+            status_cache_ttl: pool_status_ttl_from_env(),
+            send_defaults: SendDefaults::from_env(),
+            send_dir: send_dir_from_env(),
+            command_timeout: command_timeout_from_env(),
+        })
+    }
+
+    // Run a libzetta call on the blocking pool
+    async fn with_engine<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&DelegatingZfsEngine) -> libzetta::zfs::Result<T> + Send + 'static,
+    ) -> Result<T, ZfsError> {
+        let engine = self
+            .engine
+            .clone()
+            .ok_or_else(|| ZfsError::Other("libzfs_core is not initialized".to_string()))?;
+        Ok(blocking(move || f(&engine)).await?)
+    }
+
+    // List snapshots for a dataset
+    pub async fn list_snapshots(&self, dataset: &str) -> Result<Vec<String>, ZfsError> {
+        let dataset = dataset.to_string();
+        let snapshots = self.with_engine(move |engine| engine.list_snapshots(dataset)).await?;
+        Ok(snapshots
+            .into_iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    // Per-snapshot churn in one `zfs list` pass: unique bytes (used) and bytes written since the
+    // previous snapshot (written), oldest first
+    pub async fn snapshot_deltas(&self, dataset: &str) -> Result<Vec<SnapshotDelta>, ZfsError> {
+        validate_dataset_name(dataset)?;
+        let output = run_command("zfs", &["list", "-H", "-p", "-t", "snapshot", "-o", "name,creation,used,written", "-s", "creation", "-d", "1", dataset]).await?;
+
+        let mut snapshots = Vec::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, creation, used, written] = fields[..] else { continue };
+            snapshots.push(SnapshotDelta {
+                name: name.to_string(),
+                creation: creation.parse()?,
+                used: used.parse()?,
+                written: written.parse()?,
+            });
+        }
+        Ok(snapshots)
+    }
+
+    // Search snapshots across every dataset below `pool` (or all pools) with a single recursive
+    // `zfs list`; results are ordered oldest first
+    pub async fn find_snapshots(&self, pool: Option<&str>, name_contains: Option<&str>, older_than_secs: Option<u64>) -> Result<Vec<SnapshotMatch>, ZfsError> {
+        let mut args = vec!["list", "-H", "-p", "-t", "snapshot", "-o", "name,creation", "-s", "creation", "-r"];
+        if let Some(pool) = pool {
+            validate_dataset_name(pool)?;
+            args.push(pool);
+        }
+        let output = run_command("zfs", &args).await?;
+        let cutoff = match older_than_secs {
+            Some(secs) => Some(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs().saturating_sub(secs)),
+            None => None,
+        };
+
+        let mut snapshots = Vec::new();
+        for line in output.lines() {
+            let Some((full_name, creation)) = line.split_once('\t') else { continue };
+            let Some((dataset, name)) = full_name.split_once('@') else { continue };
+            let creation: u64 = creation.trim().parse()?;
+            if name_contains.is_some_and(|needle| !name.contains(needle)) || cutoff.is_some_and(|cutoff| creation > cutoff) {
+                continue;
+            }
+            snapshots.push(SnapshotMatch {
+                dataset: dataset.to_string(),
+                name: name.to_string(),
+                creation,
+            });
+        }
+        Ok(snapshots)
+    }
+
+    // Snapshots with their creation time and space usage, read in a single `zfs list` pass
+    pub async fn list_snapshots_detailed(&self, dataset: &str, recursive: bool) -> Result<Vec<SnapshotInfo>, ZfsError> {
+        validate_dataset_name(dataset)?;
+        let mut args = vec!["list", "-H", "-p", "-t", "snapshot", "-o", "name,creation,used,referenced", "-s", "creation"];
+        args.extend(if recursive { ["-r"].as_slice() } else { ["-d", "1"].as_slice() });
+        args.push(dataset);
+        let output = run_command("zfs", &args).await?;
+
+        let mut snapshots = Vec::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, creation, used, referenced] = fields[..] else { continue };
+            snapshots.push(SnapshotInfo {
+                name: name.to_string(),
+                creation: creation.parse()?,
+                used: used.parse()?,
+                referenced: referenced.parse()?,
+            });
+        }
+        Ok(snapshots)
+    }
+
+    // Create a new snapshot; with `recursive`, descendants are included in the same atomic call
+    pub async fn create_snapshot(&self, dataset: &str, snapshot_name: &str, recursive: bool) -> Result<Vec<String>, ZfsError> {
+        self.ensure_writable(dataset).await?;
+        let mut datasets = vec![dataset.to_string()];
+        if recursive {
+            let prefix = format!("{}/", dataset);
+            let root = dataset.to_string();
+            datasets.extend(
+                self.with_engine(move |engine| engine.list(root))
+                    .await?
+                    .into_iter()
+                    .filter(|(kind, _)| matches!(kind, DatasetKind::Filesystem | DatasetKind::Volume))
+                    .map(|(_, path)| path.to_string_lossy().into_owned())
+                    .filter(|name| name.starts_with(&prefix)),
+            );
+        }
+
+        let snapshots: Vec<String> = datasets.iter().map(|d| format!("{}@{}", d, snapshot_name)).collect();
+        let paths: Vec<PathBuf> = snapshots.iter().map(PathBuf::from).collect();
+        self.with_engine(move |engine| engine.snapshot(&paths, None)).await?;
+        Ok(snapshots)
+    }
+
+    // Delete a snapshot
+    pub async fn delete_snapshot(&self, dataset: &str, snapshot_name: &str) -> Result<(), ZfsError> {
+        self.ensure_writable(dataset).await?;
+        let full_path = PathBuf::from(format!("{}@{}", dataset, snapshot_name));
+        self.with_engine(move |engine| engine.destroy(full_path)).await.map_err(|e| match e {
+            ZfsError::Busy(_) => ZfsError::Busy(format!(
+                "Snapshot '{}@{}' is busy; check for user holds with GET .../holds",
+                dataset, snapshot_name
+            )),
+            e => e,
+        })?;
+        Ok(())
+    }
+
+    // Pick the snapshots a retention policy would delete, oldest first
+    pub async fn select_snapshots_to_prune(&self, dataset: &str, policy: &PruneSnapshotsRequest) -> Result<Vec<String>, ZfsError> {
+        validate_dataset_name(dataset)?;
+        if policy.keep_last.is_none() && policy.keep_newer_than_secs.is_none() {
+            return Err(ZfsError::InvalidArgument(
+                "At least one of keep_last or keep_newer_than_secs is required".to_string(),
+            ));
+        }
+
+        let mut args = vec!["list", "-H", "-p", "-t", "snapshot", "-o", "name,creation", "-s", "creation"];
+        args.extend(if policy.recursive { ["-r"].as_slice() } else { ["-d", "1"].as_slice() });
+        args.push(dataset);
+        let output = run_command("zfs", &args).await?;
+
+        // Newest first per dataset, so keep_last counts from the front
+        let mut by_dataset: BTreeMap<String, Vec<(String, u64)>> = BTreeMap::new();
+        for line in output.lines().rev() {
+            let Some((name, creation)) = line.split_once('\t') else { continue };
+            let Some((owner, snapshot_name)) = name.split_once('@') else { continue };
+            if policy.prefix.as_deref().is_some_and(|prefix| !snapshot_name.starts_with(prefix)) {
+                continue;
+            }
+            by_dataset
+                .entry(owner.to_string())
+                .or_default()
+                .push((name.to_string(), creation.trim().parse()?));
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let cutoff = policy.keep_newer_than_secs.map(|secs| now.saturating_sub(secs));
+        let keep_last = policy.keep_last.unwrap_or(0) as usize;
+        let mut selected = Vec::new();
+        for snapshots in by_dataset.into_values() {
+            for (index, (name, creation)) in snapshots.into_iter().enumerate() {
+                let kept = index < keep_last || cutoff.is_some_and(|cutoff| creation >= cutoff);
+                if !kept {
+                    selected.push((creation, name));
+                }
+            }
+        }
+        selected.sort();
+        Ok(selected.into_iter().map(|(_, name)| name).collect())
+    }
+
+    // Apply a retention policy; a snapshot that fails to delete (e.g. held) does not stop the rest
+    pub async fn prune_snapshots(&self, dataset: &str, policy: &PruneSnapshotsRequest) -> Result<Vec<BatchSnapshotResult>, ZfsError> {
+        let selected = self.select_snapshots_to_prune(dataset, policy).await?;
+        self.ensure_writable(dataset).await?;
+        let mut results = Vec::with_capacity(selected.len());
+        for snapshot in selected {
+            let (owner, snapshot_name) = snapshot.split_once('@').unwrap_or((&snapshot, ""));
+            let result = self.delete_snapshot(owner, snapshot_name).await;
+            results.push(BatchSnapshotResult {
+                status: if result.is_ok() { "deleted" } else { "error" }.to_string(),
+                message: result.err().map(|e| e.to_string()),
+                snapshot,
+            });
+        }
+        Ok(results)
+    }
+
+    // Place a user hold on a snapshot so it cannot be destroyed until released
+    pub async fn hold_snapshot(&self, dataset: &str, snapshot_name: &str, tag: &str) -> Result<(), ZfsError> {
+        let snapshot = hold_target(dataset, snapshot_name, tag)?;
+        self.ensure_writable(dataset).await?;
+        run_command("zfs", &["hold", "--", tag, &snapshot]).await?;
+        Ok(())
+    }
+
+    pub async fn release_hold(&self, dataset: &str, snapshot_name: &str, tag: &str) -> Result<(), ZfsError> {
+        let snapshot = hold_target(dataset, snapshot_name, tag)?;
+        self.ensure_writable(dataset).await?;
+        run_command("zfs", &["release", "--", tag, &snapshot]).await?;
+        Ok(())
+    }
+
+    // Datasets cloned from a snapshot, read from its `clones` property
+    pub async fn list_clones(&self, dataset: &str, snapshot_name: &str) -> Result<(String, Vec<String>), ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(snapshot_name)?;
+        let snapshot = format!("{}@{}", dataset, snapshot_name);
+        let output = run_command("zfs", &["get", "-H", "-o", "value", "clones", &snapshot]).await?;
+        let clones = output
+            .trim()
+            .split(',')
+            .map(str::trim)
+            .filter(|clone| !clone.is_empty() && *clone != "-")
+            .map(str::to_string)
+            .collect();
+        Ok((snapshot, clones))
+    }
+
+    pub async fn list_holds(&self, dataset: &str, snapshot_name: &str) -> Result<(String, Vec<SnapshotHold>), ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(snapshot_name)?;
+        let snapshot = format!("{}@{}", dataset, snapshot_name);
+        let output = run_command("zfs", &["holds", "-H", &snapshot]).await?;
+
+        let holds = output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let _name = fields.next()?;
+                Some(SnapshotHold {
+                    tag: fields.next()?.to_string(),
+                    created: fields.next().unwrap_or("").trim().to_string(),
+                })
+            })
+            .collect();
+        Ok((snapshot, holds))
+    }
+
+    // Start listing file changes between a snapshot and a later snapshot, or the live filesystem when
+    // `to` is None. Diffs of busy datasets can be very large, so entries are read one at a time.
+    pub async fn diff_snapshots(&self, from: &str, to: Option<&str>) -> Result<DiffStream, ZfsError> {
+        let (dataset, from_name) = from
+            .split_once('@')
+            .ok_or_else(|| ZfsError::InvalidArgument(format!("'{}' is not a snapshot", from)))?;
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(from_name)?;
+        if let Some(to) = to {
+            match to.split_once('@') {
+                Some((to_dataset, to_name)) => {
+                    validate_dataset_name(to_dataset)?;
+                    validate_snapshot_name(to_name)?;
+                }
+                None => validate_dataset_name(to)?,
+            }
+        }
+
+        let mut args = vec!["diff", "-H", from];
+        args.extend(to);
+        let mut child = tokio::process::Command::new("zfs")
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // Drain stderr alongside stdout so a chatty zfs cannot block on a full pipe
+        let mut stderr = child.stderr.take().expect("zfs diff stderr is piped");
+        let stderr = tokio::spawn(async move {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text).await;
+            text
+        });
+        let stdout = child.stdout.take().expect("zfs diff stdout is piped");
+        Ok(DiffStream {
+            dataset: dataset.to_string(),
+            lines: tokio::io::BufReader::new(stdout).lines(),
+            stderr: Some(stderr),
+            child,
+        })
+    }
+
+    // Rename a snapshot within its dataset and return the new full path
+    pub async fn rename_snapshot(&self, dataset: &str, old: &str, new: &str) -> Result<String, ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(old)?;
+        validate_snapshot_name(new)?;
+        self.ensure_writable(dataset).await?;
+
+        let old_path = format!("{}@{}", dataset, old);
+        let new_path = format!("{}@{}", dataset, new);
+        if !self.list_snapshots(dataset).await?.contains(&old_path) {
+            return Err(ZfsError::NotFound(format!("Snapshot '{}' does not exist", old_path)));
+        }
+
+        run_command("zfs", &["rename", &old_path, &new_path]).await?;
+        Ok(new_path)
+    }
+
+    // Create snapshots across several datasets; those sharing a pool are taken in one atomic call
+    pub async fn create_snapshots_batch(&self, items: &[BatchSnapshotItem]) -> Vec<BatchSnapshotResult> {
+        let mut results: Vec<Option<BatchSnapshotResult>> = Vec::with_capacity(items.len());
+        let mut by_pool: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+
+        for (index, item) in items.iter().enumerate() {
+            let validation = validate_dataset_name(&item.dataset).and_then(|_| validate_snapshot_name(&item.name));
+            match validation {
+                Ok(_) => {
+                    let pool = item.dataset.split('/').next().unwrap_or(&item.dataset);
+                    by_pool.entry(pool).or_default().push(index);
+                    results.push(None);
+                }
+                Err(e) => results.push(Some(BatchSnapshotResult {
+                    snapshot: format!("{}@{}", item.dataset, item.name),
+                    status: "error".to_string(),
+                    message: Some(e.to_string()),
+                })),
+            }
+        }
+
+        for (pool, indices) in &by_pool {
+            let paths: Vec<PathBuf> = indices
+                .iter()
+                .map(|&i| PathBuf::from(format!("{}@{}", items[i].dataset, items[i].name)))
+                .collect();
+            let outcome = match self.ensure_writable(pool).await {
+                Ok(()) => {
+                    let paths = paths.clone();
+                    self.with_engine(move |engine| engine.snapshot(&paths, None)).await
+                }
+                Err(e) => Err(e),
+            };
+
+            for (&index, path) in indices.iter().zip(paths) {
+                results[index] = Some(BatchSnapshotResult {
+                    snapshot: path.to_string_lossy().into_owned(),
+                    status: if outcome.is_ok() { "success" } else { "error" }.to_string(),
+                    message: outcome.as_ref().err().map(|e| e.to_string()),
+                });
+            }
+        }
+
+        results.into_iter().flatten().collect()
+    }
+
+    // Clone a snapshot read-only under a temporary mountpoint so its files can be browsed
+    pub async fn inspect_snapshot(&self, dataset: &str, snapshot_name: &str) -> Result<(String, String), ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(snapshot_name)?;
+        self.ensure_writable(dataset).await?;
+        let snapshot = format!("{}@{}", dataset, snapshot_name);
+        if !self.dataset_exists(&snapshot).await? {
+            return Err(ZfsError::NotFound(format!("Snapshot '{}' does not exist", snapshot)));
+        }
+
+        // Clones must live in the same pool as their origin. The counter keeps names unique when the
+        // same snapshot is inspected twice within the clock's resolution.
+        static INSPECTIONS: AtomicU64 = AtomicU64::new(0);
+        let pool = dataset.split('/').next().unwrap_or(dataset);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let clone = format!(
+            "{}/inspect-{}-{:x}-{:x}",
+            pool,
+            snapshot_name,
+            nanos,
+            INSPECTIONS.fetch_add(1, Ordering::Relaxed)
+        );
+        let mount_path = std::env::temp_dir()
+            .join("zfs-inspect")
+            .join(clone.replace('/', "_"))
+            .to_string_lossy()
+            .into_owned();
+
+        let mountpoint_option = format!("mountpoint={}", mount_path);
+        run_command("zfs", &["clone", "-o", "readonly=on", "-o", &mountpoint_option, &snapshot, &clone]).await?;
+
+        // From here on the clone exists; destroy it again if it cannot be mounted
+        let mounted = match run_command("zfs", &["get", "-H", "-o", "value", "mounted", &clone]).await {
+            Ok(mounted) if mounted.trim() != "yes" => run_command("zfs", &["mount", &clone]).await.map(|_| ()),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = mounted {
+            let _ = run_command("zfs", &["destroy", &clone]).await;
+            return Err(e);
+        }
+        Ok((clone, mount_path))
+    }
+
+    // Unmount and destroy an inspection clone, refusing anything that is not a clone of this snapshot
+    pub async fn cleanup_inspection(&self, dataset: &str, snapshot_name: &str, clone_name: &str) -> Result<String, ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(snapshot_name)?;
+        if !clone_name.starts_with("inspect-") || clone_name.contains('/') {
+            return Err(ZfsError::InvalidArgument(format!("'{}' is not an inspection clone", clone_name)));
+        }
+
+        let pool = dataset.split('/').next().unwrap_or(dataset);
+        let clone = format!("{}/{}", pool, clone_name);
+        validate_dataset_name(&clone)?;
+        self.ensure_writable(pool).await?;
+
+        let origin = run_command("zfs", &["get", "-H", "-o", "value", "origin", &clone]).await?;
+        if origin.trim() != format!("{}@{}", dataset, snapshot_name) {
+            return Err(ZfsError::InvalidArgument(format!("'{}' is not a clone of {}@{}", clone, dataset, snapshot_name)));
+        }
+
+        let mountpoint = run_command("zfs", &["get", "-H", "-o", "value", "mountpoint", &clone]).await?;
+        run_command("zfs", &["unmount", &clone]).await?;
+        run_command("zfs", &["destroy", &clone]).await?;
+        let _ = tokio::fs::remove_dir(mountpoint.trim()).await;
+        Ok(clone)
+    }
+
+    // Create a redaction bookmark recording which blocks the redaction snapshots changed
+    pub async fn create_redaction_bookmark(&self, dataset: &str, snapshot_name: &str, request: &CreateRedactionBookmark) -> Result<String, ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(snapshot_name)?;
+        validate_snapshot_name(&request.bookmark)
+            .map_err(|_| ZfsError::InvalidArgument(format!("Invalid bookmark name '{}'", request.bookmark)))?;
+        if request.redaction_snapshots.is_empty() {
+            return Err(ZfsError::InvalidArgument("At least one redaction snapshot is required".to_string()));
+        }
+        self.ensure_writable(dataset).await?;
+        for redaction_snapshot in &request.redaction_snapshots {
+            let (redaction_dataset, redaction_name) = redaction_snapshot
+                .split_once('@')
+                .ok_or_else(|| ZfsError::InvalidArgument(format!("Redaction snapshot '{}' must be a full dataset@snapshot name", redaction_snapshot)))?;
+            validate_dataset_name(redaction_dataset)?;
+            validate_snapshot_name(redaction_name)?;
+        }
+
+        let snapshot = format!("{}@{}", dataset, snapshot_name);
+        let mut args = vec!["redact", snapshot.as_str(), request.bookmark.as_str()];
+        args.extend(request.redaction_snapshots.iter().map(|s| s.as_str()));
+        run_command("zfs", &args).await?;
+        Ok(format!("{}#{}", dataset, request.bookmark))
+    }
+
+    // Bookmark a snapshot so it can serve as an incremental send base after the snapshot is gone
+    pub async fn create_bookmark(&self, dataset: &str, snapshot: &str, bookmark: &str) -> Result<String, ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(snapshot)?;
+        validate_snapshot_name(bookmark)
+            .map_err(|_| ZfsError::InvalidArgument(format!("Invalid bookmark name '{}'", bookmark)))?;
+        self.ensure_writable(dataset).await?;
+
+        let snapshot = format!("{}@{}", dataset, snapshot);
+        let bookmark = format!("{}#{}", dataset, bookmark);
+        run_command("zfs", &["bookmark", &snapshot, &bookmark]).await?;
+        Ok(bookmark)
+    }
+
+    // List bookmarks directly on a dataset
+    pub async fn list_bookmarks(&self, dataset: &str) -> Result<Vec<String>, ZfsError> {
+        validate_dataset_name(dataset)?;
+        let output = run_command("zfs", &["list", "-H", "-t", "bookmark", "-o", "name", "-d", "1", dataset]).await?;
+        Ok(output.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+    }
+
+    pub async fn destroy_bookmark(&self, dataset: &str, bookmark: &str) -> Result<(), ZfsError> {
+        validate_dataset_name(dataset)?;
+        validate_snapshot_name(bookmark)
+            .map_err(|_| ZfsError::InvalidArgument(format!("Invalid bookmark name '{}'", bookmark)))?;
+        self.ensure_writable(dataset).await?;
+        run_command("zfs", &["destroy", &format!("{}#{}", dataset, bookmark)]).await?;
+        Ok(())
+    }
+
+    // Build the `zfs send` arguments for a snapshot from the request flags
+    async fn send_args(&self, dataset: &str, snapshot_name: &str, request: &SendSnapshot) -> Result<Vec<String>, ZfsError> {
+        if request.rate_limit_bytes_per_sec == Some(0) {
+            return Err(ZfsError::InvalidArgument("rate_limit_bytes_per_sec must be greater than zero".to_string()));
+        }
+        if let Some(token) = &request.resume_token {
+            return resume_send_args(token, request);
+        }
+        let snapshot = format!("{}@{}", dataset, snapshot_name);
+        if !self.dataset_exists(&snapshot).await? {
+            return Err(ZfsError::NotFound(format!("Snapshot '{}' does not exist", snapshot)));
+        }
+
+        // libzetta's send has no property support, so the stream is produced by the CLI
+        let mut args = vec!["send".to_string()];
+        if request.properties {
+            args.push("-p".to_string());
+        }
+        if request.embed_data.unwrap_or(self.send_defaults.embed_data) {
+            args.push("-e".to_string());
+        }
+        if request.large_blocks.unwrap_or(self.send_defaults.large_blocks) {
+            args.push("-L".to_string());
+        }
+        if request.raw {
+            args.push("-w".to_string());
+        }
+        if request.compressed {
+            args.push("-c".to_string());
+        }
+        if request.recursive {
+            if request.redact_bookmark.is_some() {
+                return Err(ZfsError::InvalidArgument("A redacted send cannot be recursive".to_string()));
+            }
+            args.push("-R".to_string());
+        }
+        if let Some(bookmark) = &request.redact_bookmark {
+            validate_snapshot_name(bookmark)
+                .map_err(|_| ZfsError::InvalidArgument(format!("Invalid redaction bookmark '{}'", bookmark)))?;
+            args.push("--redact".to_string());
+            args.push(bookmark.clone());
+        }
+        match &request.from_snapshot {
+            Some(from) => {
+                let from = if let Some((from_dataset, bookmark)) = from.split_once('#') {
+                    // zfs send -I cannot start from a bookmark, only -i can
+                    if request.include_intermediate {
+                        return Err(ZfsError::InvalidArgument("include_intermediate cannot be used with a bookmark as from_snapshot".to_string()));
+                    }
+                    let from_dataset = if from_dataset.is_empty() { dataset } else { from_dataset };
+                    validate_dataset_name(from_dataset)?;
+                    validate_snapshot_name(bookmark)?;
+                    format!("{}#{}", from_dataset, bookmark)
+                } else if let Some((from_dataset, from_name)) = from.split_once('@') {
+                    validate_dataset_name(from_dataset)?;
+                    validate_snapshot_name(from_name)?;
+                    from.clone()
+                } else {
+                    validate_snapshot_name(from)?;
+                    format!("{}@{}", dataset, from)
+                };
+                args.push(if request.include_intermediate { "-I" } else { "-i" }.to_string());
+                args.push(from);
+            }
+            None if request.include_intermediate => {
+                return Err(ZfsError::InvalidArgument("include_intermediate requires from_snapshot".to_string()));
+            }
+            None => {}
+        }
+        args.push(snapshot);
+        Ok(args)
+    }
+
+    // The snapshots a recursive (-R) send of dataset@snapshot_name carries, parents before children
+    pub async fn recursive_send_members(&self, dataset: &str, snapshot_name: &str) -> Result<Vec<String>, ZfsError> {
+        validate_dataset_name(dataset)?;
+        let suffix = format!("@{}", snapshot_name);
+        let output = run_command("zfs", &["list", "-H", "-o", "name", "-t", "snapshot", "-r", "-s", "name", dataset]).await?;
+        Ok(output
+            .lines()
+            .filter(|name| name.ends_with(&suffix))
+            .map(|name| name.to_string())
+            .collect())
+    }
+
+    // Follow the ZFS event log (zpool events -f). Past events still in the kernel buffer come first.
+    // The reader stops, and zpool is killed, as soon as the receiver is dropped.
+    pub async fn subscribe_events(&self) -> Result<tokio::sync::mpsc::Receiver<ZfsEvent>, ZfsError> {
+        use tokio::io::AsyncBufReadExt;
+
+        let mut child = tokio::process::Command::new("zpool")
+            .args(["events", "-f", "-v", "-H"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child.stdout.take().ok_or_else(|| ZfsError::Other("Failed to capture zpool events output".to_string()))?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(64);
+
+        tokio::spawn(async move {
+            let _child = child;
+            let mut lines = tokio::io::BufReader::new(stdout).lines();
+            // A record is a header line "<date> <time> <class>" followed by indented fields
+            let mut current: Option<(String, HashMap<String, String>)> = None;
+            loop {
+                let line = tokio::select! {
+                    _ = sender.closed() => return,
+                    line = lines.next_line() => match line {
+                        Ok(Some(line)) => line,
+                        _ => return,
+                    },
+                };
+                if line.starts_with(char::is_whitespace) {
+                    if let (Some((_, fields)), Some((key, value))) = (current.as_mut(), line.trim().split_once(" = ")) {
+                        fields.insert(key.to_string(), value.to_string());
+                    }
+                    continue;
+                }
+                if let Some((class, fields)) = current.take() {
+                    if sender.send(parse_event(&class, &fields)).await.is_err() {
+                        return;
+                    }
+                }
+                if let Some(class) = line.split_whitespace().last() {
+                    current = Some((class.to_string(), HashMap::new()));
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
+    // Start `zfs send` with stdout piped so the stream can be forwarded while it is produced.
+    // zfs is killed if the child is dropped, e.g. when the HTTP client disconnects.
+    pub async fn spawn_send_stream(&self, dataset: &str, snapshot_name: &str, request: &SendSnapshot) -> Result<tokio::process::Child, ZfsError> {
+        let args = self.send_args(dataset, snapshot_name, request).await?;
+        let child = tokio::process::Command::new("zfs")
+            .args(&args)
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        Ok(child)
+    }
+
+    // Send a snapshot stream to a file on the agent host; returns bytes written and time taken
+    pub async fn send_snapshot_to_file(&self, dataset: &str, snapshot_name: &str, request: &SendSnapshot, output_file: &str) -> Result<(u64, Duration), ZfsError> {
+        let args = self.send_args(dataset, snapshot_name, request).await?;
+        let output_path = stream_file_path(&self.send_dir, output_file).await?;
+
+        // create_new refuses existing files (and symlinks), so everything removed below was created here
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&output_path)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => ZfsError::AlreadyExists(format!("Output file '{}' already exists", output_file)),
+                _ => ZfsError::from(e),
+            })?
+            .into_std()
+            .await;
+        let rate_limit = request.rate_limit_bytes_per_sec;
+        let sent = blocking(move || -> Result<(u64, Duration), ZfsError> {
+            let mut send = Command::new("zfs")
+                .args(&args)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            let mut send_stdout = send.stdout.take().ok_or_else(|| ZfsError::Other("Failed to capture zfs send output".to_string()))?;
+            let copied = copy_throttled(&mut send_stdout, &mut file, rate_limit);
+            drop(send_stdout);
+            let output = send.wait_with_output()?;
+            if !output.status.success() {
+                return Err(ZfsError::from_stderr(format!("zfs send failed: {}", String::from_utf8_lossy(&output.stderr).trim())));
+            }
+            Ok(copied?)
+        })
+        .await;
+        // Whatever failed, the file holds no complete stream
+        if sent.is_err() {
+            let _ = tokio::fs::remove_file(&output_path).await;
+        }
+        sent
+    }
+
+    // Send a snapshot stream into a file on another host by piping it through `ssh host 'cat > path'`.
+    // The stream is copied through the agent so it can be throttled; returns bytes sent and time taken.
+    pub async fn send_snapshot_to_remote_file(&self, dataset: &str, snapshot_name: &str, request: &SendSnapshot, target: &RemoteFileTarget) -> Result<(u64, Duration), ZfsError> {
+        validate_remote_host(&target.remote_host)?;
+        if target.remote_path.is_empty() || target.remote_path.contains(['\n', '\0']) {
+            return Err(ZfsError::InvalidArgument(format!("Invalid remote path '{}'", target.remote_path)));
+        }
+        let args = self.send_args(dataset, snapshot_name, request).await?;
+
+        let mut send = Command::new("zfs")
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut send_stdout = send.stdout.take().ok_or_else(|| ZfsError::Other("Failed to capture zfs send output".to_string()))?;
+
+        // The remote command goes through the remote user's shell, so the path must be quoted
+        let remote_command = format!("cat > {}", shell_quote(&target.remote_path));
+        let mut ssh = Command::new("ssh")
+            .args(["-o", "BatchMode=yes", "--", &target.remote_host, &remote_command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut ssh_stdin = ssh.stdin.take().ok_or_else(|| ZfsError::Other("Failed to open ssh input".to_string()))?;
+        let rate_limit = request.rate_limit_bytes_per_sec;
+        let (copied, ssh_output, send_output) = blocking(move || {
+            let copied = copy_throttled(&mut send_stdout, &mut ssh_stdin, rate_limit);
+            // Closing both ends lets ssh see EOF and stops zfs send if ssh went away early
+            drop(ssh_stdin);
+            drop(send_stdout);
+            (copied, ssh.wait_with_output(), send.wait_with_output())
+        })
+        .await;
+        let (ssh_output, send_output) = (ssh_output?, send_output?);
+
+        if !send_output.status.success() {
+            return Err(ZfsError::from_stderr(format!("zfs send failed: {}", String::from_utf8_lossy(&send_output.stderr).trim())));
+        }
+        if !ssh_output.status.success() {
+            return Err(ZfsError::from_stderr(format!("Writing to {}:{} failed: {}", target.remote_host, target.remote_path, String::from_utf8_lossy(&ssh_output.stderr).trim())));
+        }
+        Ok(copied?)
+    }
+
+    // Receive a send stream from a file on the agent host into `target`
+    pub async fn receive_snapshot_from_file(&self, target: &str, request: &ReceiveSnapshot) -> Result<(), ZfsError> {
+        let args = receive_args(target, request.force, request.discard_first_element, request.use_last_element, request.resumable)?;
+        self.ensure_writable(target).await?;
+        let file = open_receive_input(&self.send_dir, &request.input_file).await?;
+        self.receive_from(&args, file).await
+    }
+
+    // Receive a send stream read from any source, e.g. an HTTP request body, into `target`
+    pub async fn receive_snapshot_from_stream<R: AsyncRead + Unpin>(&self, target: &str, options: &ReceiveStreamQuery, source: R) -> Result<(), ZfsError> {
+        let args = receive_args(target, options.force, options.discard_first_element, options.use_last_element, options.resumable)?;
+        self.ensure_writable(target).await?;
+        self.receive_from(&args, source).await
+    }
+
+    // Feed `source` into `zfs receive` on stdin. If zfs exits early the copy fails with a broken
+    // pipe, so the exit status and stderr are checked before the copy result. A stalled copy or a
+    // zfs that never exits is killed after the command timeout and reported as a timeout.
+    async fn receive_from<R: AsyncRead + Unpin>(&self, args: &[&str], mut source: R) -> Result<(), ZfsError> {
+        let mut child = tokio::process::Command::new("zfs")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let mut stdin = child.stdin.take().expect("zfs receive stdin is piped");
+        let copied = copy_with_stall_timeout(&mut source, &mut stdin, self.command_timeout).await;
+        drop(stdin);
+        if let Err(e @ ZfsError::Timeout(_)) = copied {
+            return Err(e);
+        }
+
+        // Dropping the child on timeout kills zfs
+        let output = tokio::time::timeout(self.command_timeout, child.wait_with_output())
+            .await
+            .map_err(|_| ZfsError::Timeout(format!(
+                "zfs receive did not finish within {}s of its input ending and was killed",
+                self.command_timeout.as_secs()
+            )))??;
+        if !output.status.success() {
+            return Err(ZfsError::from_stderr(format!("zfs receive failed: {}", String::from_utf8_lossy(&output.stderr).trim())));
+        }
+        copied?;
+        Ok(())
+    }
+
+    // Filesystems of a pool with their creation time (seconds since the epoch)
+    pub async fn list_datasets_with_creation(&self, pool: &str) -> Result<Vec<(String, u64)>, ZfsError> {
+        validate_dataset_name(pool)?;
+        let output = run_command("zfs", &["list", "-H", "-p", "-r", "-t", "filesystem", "-o", "name,creation", pool]).await?;
+        let mut datasets = Vec::new();
+        for line in output.lines() {
+            let Some((name, creation)) = line.split_once('\t') else { continue };
+            datasets.push((name.to_string(), creation.trim().parse()?));
+        }
+        Ok(datasets)
+    }
+
+    // Filesystems and volumes below `root` as a nested tree, read in a single `zfs list -r` pass
+    pub async fn list_dataset_tree(&self, root: &str) -> Result<DatasetNode, ZfsError> {
+        validate_dataset_name(root)?;
+        let output = run_command("zfs", &[
+            "list", "-H", "-p", "-r", "-t", "filesystem,volume",
+            "-o", "name,type,used,avail,refer,mountpoint", root,
+        ]).await?;
+
+        let mut children: HashMap<String, Vec<DatasetNode>> = HashMap::new();
+        let mut root_node = None;
+        for line in output.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, kind, used, available, referenced, mountpoint] = fields[..] else { continue };
+            let node = DatasetNode {
+                name: name.to_string(),
+                kind: kind.to_string(),
+                used: used.parse()?,
+                available: available.parse()?,
+                referenced: referenced.parse()?,
+                mountpoint: Some(mountpoint.to_string()).filter(|m| m.starts_with('/')),
+                children: Vec::new(),
+            };
+            match name.rsplit_once('/') {
+                _ if name == root => root_node = Some(node),
+                Some((parent, _)) => children.entry(parent.to_string()).or_default().push(node),
+                None => {}
+            }
+        }
+
+        fn attach(node: &mut DatasetNode, children: &mut HashMap<String, Vec<DatasetNode>>) {
+            node.children = children.remove(&node.name).unwrap_or_default();
+            for child in &mut node.children {
+                attach(child, children);
+            }
+        }
+        let mut root_node = root_node.ok_or_else(|| ZfsError::NotFound(format!("Dataset '{}' does not exist", root)))?;
+        attach(&mut root_node, &mut children);
+        Ok(root_node)
+    }
+
+    // Find the resume token left by an interrupted `zfs receive -s`. With -d or -e the partial
+    // dataset is created below `target`, so its descendants are searched too.
+    pub async fn receive_resume_token(&self, target: &str) -> Option<String> {
+        validate_dataset_name(target).ok()?;
+        let output = run_command("zfs", &["get", "-r", "-H", "-o", "value", "receive_resume_token", target]).await.ok()?;
+        output
+            .lines()
+            .map(str::trim)
+            .find(|token| !token.is_empty() && *token != "-")
+            .map(str::to_string)
+    }
+
+    pub async fn list_datasets(&self, pool: &str) -> Result<Vec<String>, ZfsError> {
+        let pool = pool.to_string();
+        let datasets = self.with_engine(move |engine| engine.list_filesystems(pool)).await?;
+        Ok(datasets
+            .into_iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    // Returns any parents created on the way followed by the dataset itself
+    pub async fn create_dataset(&self, request: CreateDataset) -> Result<Vec<String>, ZfsError> {
+        validate_dataset_name(&request.name)?;
+        self.ensure_writable(&request.name).await?;
+        let kind = match request.kind.to_lowercase().as_str() {
+            "filesystem" => DatasetKind::Filesystem,
+            "volume" => DatasetKind::Volume,
+            _ => return Err(ZfsError::InvalidArgument("Invalid dataset kind. Must be 'filesystem' or 'volume'".to_string())),
+        };
+
+        if let Some(properties) = &request.properties {
+            for (property, value) in properties {
+                if !is_valid_property_name(property) {
+                    return Err(ZfsError::InvalidArgument(format!("Invalid property name '{}'", property)));
+                }
+                validate_property_value(property, value)?;
+            }
+            validate_block_size_properties(properties, matches!(kind, DatasetKind::Volume))?;
+        }
+        let encryption = encryption_options(&request)?;
+
+        let mut created = Vec::new();
+        if request.create_parents {
+            let components: Vec<&str> = request.name.split('/').collect();
+            for depth in 2..components.len() {
+                let ancestor = components[..depth].join("/");
+                if self.dataset_exists(&ancestor).await? {
+                    continue;
+                }
+                let parent_request = CreateDatasetRequest::builder()
+                    .name(PathBuf::from(&ancestor))
+                    .kind(DatasetKind::Filesystem)
+                    .build().map_err(ZfsError::InvalidArgument)?;
+                if let Err(e) = self.with_engine(move |engine| engine.create(parent_request)).await {
+                    return Err(self.rollback_created(e, &created).await);
+                }
+                created.push(ancestor);
+            }
+        }
+
+        let result = match encryption {
+            Some(options) => create_encrypted(&request, matches!(kind, DatasetKind::Volume), &options).await,
+            None => {
+                let dataset_request = CreateDatasetRequest::builder()
+                    .name(PathBuf::from(&request.name))
+                    .kind(kind)
+                    .user_properties(request.properties)
+                    .build().map_err(ZfsError::InvalidArgument)?;
+                self.with_engine(move |engine| engine.create(dataset_request)).await
+            }
+        };
+        if let Err(e) = result {
+            return Err(self.rollback_created(e, &created).await);
+        }
+        created.push(request.name);
+        Ok(created)
+    }
+
+    // Create a zvol with an explicit size and block size; returns its /dev/zvol device path
+    pub async fn create_volume(&self, request: &CreateVolumeRequest) -> Result<String, ZfsError> {
+        validate_dataset_name(&request.name)?;
+        self.ensure_writable(&request.name).await?;
+        if request.size_bytes == 0 {
+            return Err(ZfsError::InvalidArgument("Volume size must be greater than zero".to_string()));
+        }
+        if let Some(block_size) = request.block_size {
+            if !block_size.is_power_of_two() || !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) {
+                return Err(ZfsError::InvalidArgument(format!(
+                    "Invalid block_size {}: must be a power of two between 512 and 16M",
+                    block_size
+                )));
+            }
+            if !request.size_bytes.is_multiple_of(block_size) {
+                return Err(ZfsError::InvalidArgument(format!(
+                    "Volume size {} must be a multiple of block_size {}",
+                    request.size_bytes, block_size
+                )));
+            }
+        }
+
+        let mut options = Vec::new();
+        for (property, value) in request.properties.iter().flatten() {
+            if matches!(property.as_str(), "volsize" | "volblocksize") {
+                return Err(ZfsError::InvalidArgument(format!(
+                    "Set '{}' with size_bytes or block_size, not properties",
+                    property
+                )));
+            }
+            if !is_valid_property_name(property) {
+                return Err(ZfsError::InvalidArgument(format!("Invalid property name '{}'", property)));
+            }
+            validate_property_value(property, value)?;
+            options.push(format!("{}={}", property, value));
+        }
+
+        let size = request.size_bytes.to_string();
+        let block_size = request.block_size.map(|b| b.to_string());
+        let mut args = vec!["create"];
+        if request.sparse {
+            args.push("-s");
+        }
+        args.extend(["-V", size.as_str()]);
+        if let Some(block_size) = &block_size {
+            args.extend(["-b", block_size.as_str()]);
+        }
+        for option in &options {
+            args.extend(["-o", option.as_str()]);
+        }
+        args.push(&request.name);
+        run_command("zfs", &args).await?;
+
+        Ok(format!("/dev/zvol/{}", request.name))
+    }
+
+    // Change a zvol's volsize. Growing is always safe; shrinking below the data already written
+    // would cut it off, so that is refused.
+    pub async fn resize_volume(&self, name: &str, new_size_bytes: u64) -> Result<String, ZfsError> {
+        validate_dataset_name(name)?;
+        self.ensure_writable(name).await?;
+        let (dataset_type, _, _) = self.get_property(name, "type").await?;
+        if dataset_type != "volume" {
+            return Err(ZfsError::InvalidArgument(format!("'{}' is a {}, not a volume", name, dataset_type)));
+        }
+
+        let block_size: u64 = self.get_property(name, "volblocksize").await?.0.parse()?;
+        if new_size_bytes == 0 || !new_size_bytes.is_multiple_of(block_size) {
+            return Err(ZfsError::InvalidArgument(format!(
+                "Volume size {} must be a non-zero multiple of the volume block size {}",
+                new_size_bytes, block_size
+            )));
+        }
+        let written: u64 = self.get_property(name, "logicalreferenced").await?.0.parse()?;
+        if new_size_bytes < written {
+            return Err(ZfsError::InvalidArgument(format!(
+                "Cannot shrink '{}' to {} bytes: {} bytes of data are already written",
+                name, new_size_bytes, written
+            )));
+        }
+
+        let props = HashMap::from([("volsize".to_string(), new_size_bytes.to_string())]);
+        self.set_properties(name, props).await?;
+        Ok(format!("/dev/zvol/{}", name))
+    }
+
+    // Destroy parents created for a failed create_dataset, newest first, and name any left behind
+    async fn rollback_created(&self, error: ZfsError, created: &[String]) -> ZfsError {
+        if created.is_empty() {
+            return error;
+        }
+        let mut left_behind: Vec<&str> = Vec::new();
+        for name in created.iter().rev() {
+            let owned = name.clone();
+            if self.with_engine(move |engine| engine.destroy(owned)).await.is_err() {
+                left_behind.push(name);
+            }
+        }
+        if left_behind.is_empty() {
+            error.with_context(&format!("rolled back created parents: {}", created.join(", ")))
+        } else {
+            error.with_context(&format!("could not roll back created parents: {}", left_behind.join(", ")))
+        }
+    }
+
+    pub async fn delete_dataset(&self, name: &str) -> Result<(), ZfsError> {
+        self.ensure_writable(name).await?;
+        let name = name.to_string();
+        self.with_engine(move |engine| engine.destroy(name)).await?;
+        Ok(())
+    }
+
+    // Rename a dataset within its pool; ZFS cannot move datasets between pools
+    pub async fn rename_dataset(&self, from: &str, to: &str, force_unmount: bool) -> Result<(), ZfsError> {
+        validate_dataset_name(from)?;
+        validate_dataset_name(to)?;
+        let pool_of = |name: &str| name.split('/').next().unwrap_or("").to_string();
+        if pool_of(from) != pool_of(to) {
+            return Err(ZfsError::InvalidArgument(format!("Cannot rename '{}' to '{}': datasets cannot be moved between pools", from, to)));
+        }
+        self.ensure_writable(from).await?;
+
+        let mut args = vec!["rename"];
+        if force_unmount {
+            args.push("-f");
+        }
+        args.push(from);
+        args.push(to);
+
+        // libzetta's ZfsEngine has no rename, and raw lzc_rename neither unmounts nor remounts the
+        // filesystem or its children (nor supports -f), so the CLI is the only safe path here
+        match run_command("zfs", &args).await {
+            Ok(_) => Ok(()),
+            Err(ZfsError::Busy(_)) => {
+                Err(ZfsError::Busy(format!("Dataset '{}' is mounted or busy; retry with force_unmount", from)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Mount a filesystem; one that is already mounted is reported rather than treated as an error
+    pub async fn mount_dataset(&self, name: &str) -> Result<String, ZfsError> {
+        validate_dataset_name(name)?;
+        match run_command("zfs", &["mount", name]).await {
+            Ok(_) => Ok(format!("Dataset '{}' mounted", name)),
+            Err(e) if e.to_string().contains("already mounted") => Ok(format!("Dataset '{}' is already mounted", name)),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn unmount_dataset(&self, name: &str, force: bool) -> Result<String, ZfsError> {
+        validate_dataset_name(name)?;
+        let mut args = vec!["unmount"];
+        if force {
+            args.push("-f");
+        }
+        args.push(name);
+
+        match run_command("zfs", &args).await {
+            Ok(_) => Ok(format!("Dataset '{}' unmounted", name)),
+            Err(e) if e.to_string().contains("not currently mounted") => Ok(format!("Dataset '{}' is not mounted", name)),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Load an encrypted dataset's key; an inline key goes to zfs on stdin via `-L prompt`.
+    // Returns the resulting keystatus.
+    pub async fn load_key(&self, dataset: &str, key: Option<&str>, recursive: bool) -> Result<String, ZfsError> {
+        validate_dataset_name(dataset)?;
+        let (keyformat, _, _) = self.get_property(dataset, "keyformat").await?;
+        if keyformat == "none" {
+            return Err(ZfsError::InvalidArgument(format!("Dataset '{}' is not encrypted", dataset)));
+        }
+
+        let result = match key {
+            Some(_) if recursive => {
+                return Err(ZfsError::InvalidArgument("An inline key cannot be used with recursive load-key".to_string()));
+            }
+            Some(key) => {
+                validate_key(&keyformat, key)?;
+                run_command_with_stdin("zfs", &["load-key", "-L", "prompt", dataset], key.as_bytes()).await
+            }
+            None if recursive => run_command("zfs", &["load-key", "-r", dataset]).await,
+            None => run_command("zfs", &["load-key", dataset]).await,
+        };
+        match result {
+            Ok(_) => {}
+            Err(e) if e.to_string().contains("Key already loaded") => {}
+            Err(e) => return Err(e),
+        }
+        Ok(self.get_property(dataset, "keystatus").await?.0)
+    }
+
+    // Unload an encrypted dataset's key; zfs refuses while the dataset is mounted
+    pub async fn unload_key(&self, dataset: &str, recursive: bool) -> Result<String, ZfsError> {
+        validate_dataset_name(dataset)?;
+        let mut args = vec!["unload-key"];
+        if recursive {
+            args.push("-r");
+        }
+        args.push(dataset);
+
+        match run_command("zfs", &args).await {
+            Ok(_) => {}
+            Err(e) if e.to_string().contains("Key already unloaded") => {}
+            Err(e) => return Err(e),
+        }
+        Ok(self.get_property(dataset, "keystatus").await?.0)
+    }
+
+    // Rotate the wrapping key of an encryption root with `zfs change-key`; the data itself is
+    // not re-encrypted. A new inline key goes to zfs on stdin.
+    pub async fn change_key(
+        &self,
+        dataset: &str,
+        new_key: Option<&str>,
+        new_keyformat: Option<&str>,
+        new_keylocation: Option<&str>,
+    ) -> Result<(), ZfsError> {
+        validate_dataset_name(dataset)?;
+        self.ensure_writable(dataset).await?;
+        let (encryption_root, _, _) = self.get_property(dataset, "encryptionroot").await?;
+        if encryption_root.is_empty() || encryption_root == "-" {
+            return Err(ZfsError::InvalidArgument(format!("Dataset '{}' is not encrypted", dataset)));
+        }
+        if encryption_root != dataset {
+            return Err(ZfsError::InvalidArgument(format!(
+                "Dataset '{}' is not an encryption root; it inherits its key from '{}', so change the key there",
+                dataset, encryption_root
+            )));
+        }
+
+        let keyformat = match new_keyformat {
+            Some(keyformat) => keyformat.to_string(),
+            None => self.get_property(dataset, "keyformat").await?.0,
+        };
+        let keylocation = match new_keylocation {
+            Some(keylocation) => {
+                validate_keylocation(keylocation)?;
+                keylocation.to_string()
+            }
+            None => self.get_property(dataset, "keylocation").await?.0,
+        };
+
+        let mut options = Vec::new();
+        if let Some(keyformat) = new_keyformat {
+            options.push(format!("keyformat={}", keyformat));
+        }
+        if let Some(keylocation) = new_keylocation {
+            options.push(format!("keylocation={}", keylocation));
+        }
+        let mut args = vec!["change-key"];
+        for option in &options {
+            args.extend(["-o", option.as_str()]);
+        }
+        args.push(dataset);
+
+        match (keylocation.as_str(), new_key) {
+            ("prompt", Some(key)) => {
+                validate_key(&keyformat, key)?;
+                run_command_with_stdin("zfs", &args, key.as_bytes()).await?;
+            }
+            ("prompt", None) => {
+                return Err(ZfsError::InvalidArgument("keylocation 'prompt' requires the new key to be given in 'new_key'".to_string()));
+            }
+            (_, Some(_)) => {
+                return Err(ZfsError::InvalidArgument("new_key cannot be given when keylocation points to a file or URL".to_string()));
+            }
+            (_, None) => {
+                if !["passphrase", "hex", "raw"].contains(&keyformat.as_str()) {
+                    return Err(ZfsError::InvalidArgument(format!("Invalid keyformat '{}': must be passphrase, hex or raw", keyformat)));
+                }
+                run_command("zfs", &args).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Report how much space deleting these snapshots together would free, without deleting anything.
+    // Blocks shared only between the listed snapshots count, which per-snapshot `used` misses.
+    pub async fn preview_snapshot_deletion(&self, dataset: &str, snapshots: &[String]) -> Result<(Vec<String>, u64), ZfsError> {
+        validate_dataset_name(dataset)?;
+        if snapshots.is_empty() {
+            return Err(ZfsError::InvalidArgument("At least one snapshot name is required".to_string()));
+        }
+        for snapshot in snapshots {
+            validate_snapshot_name(snapshot)?;
+        }
+
+        let target = format!("{}@{}", dataset, snapshots.join(","));
+        let output = run_command("zfs", &["destroy", "-n", "-v", "-p", &target]).await?;
+
+        let mut would_destroy = Vec::new();
+        let mut reclaimable_bytes = 0;
+        for line in output.lines() {
+            match line.split_once('\t') {
+                Some(("destroy", name)) => would_destroy.push(name.trim().to_string()),
+                Some(("reclaim", bytes)) => reclaimable_bytes = bytes.trim().parse()?,
+                _ => {}
+            }
+        }
+        Ok((would_destroy, reclaimable_bytes))
+    }
+
+    // Set several properties with a single `zfs set`; everything is validated before zfs runs
+    pub async fn set_properties(&self, dataset: &str, props: HashMap<String, String>) -> Result<(), ZfsError> {
+        validate_dataset_name(dataset)?;
+        if props.is_empty() {
+            return Err(ZfsError::InvalidArgument("No properties given".to_string()));
+        }
+        self.ensure_writable(dataset).await?;
+        let mut assignments = Vec::with_capacity(props.len());
+        for (property, value) in props.iter().collect::<BTreeMap<_, _>>() {
+            if !is_valid_property_name(property) {
+                return Err(ZfsError::InvalidArgument(format!("Invalid property name '{}'", property)));
+            }
+            validate_property_value(property, value)?;
+            assignments.push(format!("{}={}", property, value));
+        }
+
+        let mut args = vec!["set"];
+        args.extend(assignments.iter().map(String::as_str));
+        args.push(dataset);
+        run_command("zfs", &args).await?;
+        Ok(())
+    }
+
+    // After a failed `zfs set`, find which of the requested values are nevertheless in effect locally
+    pub async fn applied_properties(&self, dataset: &str, props: &HashMap<String, String>) -> Vec<String> {
+        let names: Vec<&str> = props.keys().map(String::as_str).collect();
+        let names = names.join(",");
+        let Ok(output) = run_command("zfs", &["get", "-H", "-p", "-o", "property,value,source", &names, dataset]).await else {
+            return Vec::new();
+        };
+
+        let mut applied = Vec::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [property, value, "local"] = fields[..] else { continue };
+            let Some(requested) = props.get(property) else { continue };
+            // -p prints sizes as raw bytes, so compare sizes numerically
+            let matches = value == requested
+                || parse_size(requested).is_some_and(|bytes| value.parse() == Ok(bytes));
+            if matches {
+                applied.push(property.to_string());
+            }
+        }
+        applied.sort();
+        applied
+    }
+
+    // Read one property with its source, without fetching the whole property set
+    pub async fn get_property(&self, dataset: &str, property: &str) -> Result<(String, String, Option<String>), ZfsError> {
+        validate_dataset_name(dataset)?;
+        if !is_valid_property_name(property) {
+            return Err(ZfsError::InvalidArgument(format!("Invalid property name '{}'", property)));
+        }
+        let output = run_command("zfs", &["get", "-H", "-p", "-o", "value,source", property, dataset]).await?;
+        let line = output.lines().next().unwrap_or("");
+        let (value, source) = line.split_once('\t').unwrap_or((line, "-"));
+
+        // zfs reports "-" as the source of properties that have none, e.g. read-only statistics
+        let (source, inherited_from) = match source.trim() {
+            "-" => ("none".to_string(), None),
+            s => match s.strip_prefix("inherited from ") {
+                Some(origin) => ("inherited".to_string(), Some(origin.to_string())),
+                None => (s.to_string(), None),
+            },
+        };
+        Ok((value.to_string(), source, inherited_from))
+    }
+
+    // The snapshot a clone was created from; None for datasets that are not clones
+    pub async fn get_origin(&self, dataset: &str) -> Result<Option<String>, ZfsError> {
+        let (origin, _, _) = self.get_property(dataset, "origin").await?;
+        Ok(Some(origin).filter(|origin| !origin.is_empty() && origin != "-"))
+    }
+
+    // Space accounting for a dataset or snapshot ("dataset@snap") from a single `zfs get`
+    pub async fn get_space_usage(&self, name: &str) -> Result<SpaceUsage, ZfsError> {
+        match name.split_once('@') {
+            Some((dataset, snapshot)) => {
+                validate_dataset_name(dataset)?;
+                validate_snapshot_name(snapshot)?;
+            }
+            None => validate_dataset_name(name)?,
+        }
+        let output = run_command("zfs", &[
+            "get", "-H", "-p", "-o", "property,value",
+            "used,available,usedbydataset,usedbysnapshots,usedbychildren,usedbyrefreservation",
+            name,
+        ]).await?;
+
+        // Properties that do not apply (e.g. usedbychildren on a snapshot) read as "-"
+        let values: HashMap<&str, Option<u64>> = output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(property, value)| (property, value.trim().parse().ok()))
+            .collect();
+        let get = |property: &str| values.get(property).copied().flatten();
+        Ok(SpaceUsage {
+            used: get("used").ok_or_else(|| ZfsError::Other(format!("zfs get returned no 'used' value for '{}'", name)))?,
+            available: get("available"),
+            usedbydataset: get("usedbydataset"),
+            usedbysnapshots: get("usedbysnapshots"),
+            usedbychildren: get("usedbychildren"),
+            usedbyrefreservation: get("usedbyrefreservation"),
+        })
+    }
+
+    // Clear a locally set property so the inherited or default value applies again
+    pub async fn inherit_property(&self, dataset: &str, property: &str, recursive: bool) -> Result<(), ZfsError> {
+        validate_dataset_name(dataset)?;
+        if !is_valid_property_name(property) {
+            return Err(ZfsError::InvalidArgument(format!("Invalid property name '{}'", property)));
+        }
+        self.ensure_writable(dataset).await?;
+        let mut args = vec!["inherit"];
+        if recursive {
+            args.push("-r");
+        }
+        args.push(property);
+        args.push(dataset);
+        run_command("zfs", &args).await?;
+        Ok(())
+    }
+
+    // Apply quota and reservation limits given in bytes in a single `zfs set`; None clears a limit.
+    // Returns the "property=value" assignments that were applied.
+    pub async fn set_space_limits(&self, dataset: &str, request: &QuotaRequest) -> Result<Vec<String>, ZfsError> {
+        validate_dataset_name(dataset)?;
+        self.ensure_writable(dataset).await?;
+        let limits = [
+            ("quota", request.quota_bytes),
+            ("reservation", request.reservation_bytes),
+            ("refquota", request.refquota_bytes),
+            ("refreservation", request.refreservation_bytes),
+        ];
+        let assignments: Vec<String> = limits
+            .iter()
+            .filter_map(|(property, limit)| {
+                limit.map(|bytes| match bytes {
+                    Some(bytes) => format!("{}={}", property, bytes),
+                    None => format!("{}=none", property),
+                })
+            })
+            .collect();
+        if assignments.is_empty() {
+            return Err(ZfsError::InvalidArgument("At least one of quota_bytes, reservation_bytes, refquota_bytes or refreservation_bytes is required".to_string()));
+        }
+
+        // A reservation can use space already held by the current one plus what is still available
+        for (property, requested) in [("reservation", request.reservation_bytes), ("refreservation", request.refreservation_bytes)] {
+            if let Some(Some(requested)) = requested {
+                let output = run_command("zfs", &["get", "-H", "-p", "-o", "value", &format!("available,{}", property), dataset]).await?;
+                let mut values = output.lines().map(|v| v.trim().parse::<u64>().unwrap_or(0));
+                let available = values.next().unwrap_or(0);
+                let current = values.next().unwrap_or(0);
+                if requested > available + current {
+                    return Err(ZfsError::InvalidArgument(format!(
+                        "{} of {} bytes exceeds the {} bytes available to '{}'",
+                        property,
+                        requested,
+                        available + current,
+                        dataset
+                    )));
+                }
+            }
+        }
+
+        let mut args = vec!["set"];
+        args.extend(assignments.iter().map(|a| a.as_str()));
+        args.push(dataset);
+        run_command("zfs", &args).await?;
+        Ok(assignments)
+    }
+
+    // Toggle visibility of the .zfs snapshot directory and return the effective value
+    pub async fn set_snapdir(&self, dataset: &str, visible: bool) -> Result<String, ZfsError> {
+        validate_dataset_name(dataset)?;
+        self.ensure_writable(dataset).await?;
+        let value = if visible { "snapdir=visible" } else { "snapdir=hidden" };
+        run_command("zfs", &["set", value, dataset]).await?;
+
+        let effective = run_command("zfs", &["get", "-H", "-o", "value", "snapdir", dataset]).await?;
+        Ok(effective.trim().to_string())
+    }
+
+    // Share a dataset over NFS or SMB by setting sharenfs/sharesmb, then make sure it is exported.
+    // Returns the resulting (sharenfs, sharesmb) values.
+    pub async fn set_share(&self, dataset: &str, protocol: &str, options: Option<&str>) -> Result<(String, String), ZfsError> {
+        validate_dataset_name(dataset)?;
+        let property = share_property(protocol)?;
+        let value = options.unwrap_or("on");
+        validate_share_options(value)?;
+        self.ensure_writable(dataset).await?;
+        run_command("zfs", &["set", &format!("{}={}", property, value), dataset]).await?;
+
+        // Setting the property shares a mounted dataset already; zfs share covers the rest
+        if let Err(e) = run_command("zfs", &["share", dataset]).await {
+            if !e.to_string().contains("already shared") {
+                return Err(e);
+            }
+        }
+        self.get_share_state(dataset).await
+    }
+
+    // Stop sharing over one protocol; turning the property off also unshares the dataset
+    pub async fn unshare(&self, dataset: &str, protocol: &str) -> Result<(String, String), ZfsError> {
+        validate_dataset_name(dataset)?;
+        let property = share_property(protocol)?;
+        self.ensure_writable(dataset).await?;
+        run_command("zfs", &["set", &format!("{}=off", property), dataset]).await?;
+        self.get_share_state(dataset).await
+    }
+
+    async fn get_share_state(&self, dataset: &str) -> Result<(String, String), ZfsError> {
+        let output = run_command("zfs", &["get", "-H", "-o", "property,value", "sharenfs,sharesmb", dataset]).await?;
+        let mut state = (String::new(), String::new());
+        for (property, value) in output.lines().filter_map(|line| line.split_once('\t')) {
+            match property {
+                "sharenfs" => state.0 = value.to_string(),
+                "sharesmb" => state.1 = value.to_string(),
+                _ => {}
+            }
+        }
+        Ok(state)
+    }
+
+    // Read a subset of properties for several datasets with a single `zfs get` call
+    pub async fn get_properties_batch(
+        &self,
+        datasets: &[String],
+        fields: &[String],
+    ) -> Result<HashMap<String, HashMap<String, String>>, ZfsError> {
+        if datasets.is_empty() || fields.is_empty() {
+            return Err(ZfsError::InvalidArgument("Both 'datasets' and 'fields' must be non-empty".to_string()));
+        }
+        for dataset in datasets {
+            validate_dataset_name(dataset)?;
+        }
+        if let Some(field) = fields.iter().find(|f| !is_valid_property_name(f)) {
+            return Err(ZfsError::InvalidArgument(format!("Invalid property name '{}'", field)));
+        }
+
+        let field_list = fields.join(",");
+        let mut args = vec!["get", "-H", "-p", "-o", "name,property,value", field_list.as_str()];
+        args.extend(datasets.iter().map(|d| d.as_str()));
+        let output = run_command("zfs", &args).await?;
+
+        let mut result: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for line in output.lines() {
+            let fields: Vec<&str> = line.splitn(3, '\t').collect();
+            if let [name, property, value] = fields[..] {
+                result
+                    .entry(name.to_string())
+                    .or_default()
+                    .insert(property.to_string(), value.to_string());
+            }
+        }
+        Ok(result)
+    }
+
+    // Find the mounted filesystem whose mountpoint is the longest prefix of `path`
+    pub async fn find_dataset_by_mountpoint(&self, path: &str) -> Result<Option<(String, String)>, ZfsError> {
+        if !path.starts_with('/') {
+            return Err(ZfsError::InvalidArgument(format!("Path '{}' must be absolute", path)));
+        }
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
+
+        let output = run_command("zfs", &["list", "-H", "-t", "filesystem", "-o", "name,mountpoint,mounted"]).await?;
+        let mut best: Option<(String, String)> = None;
+        for line in output.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 3 || fields[2] != "yes" || !fields[1].starts_with('/') {
+                continue;  // skips "none", "legacy" and unmounted filesystems
+            }
+            let (name, mountpoint) = (fields[0], fields[1]);
+
+            let owns_path = mountpoint == "/"
+                || path == mountpoint
+                || path.starts_with(&format!("{}/", mountpoint));
+            let is_longer = match &best {
+                Some((_, current)) => mountpoint.len() > current.len(),
+                None => true,
+            };
+            if owns_path && is_longer {
+                best = Some((name.to_string(), mountpoint.to_string()));
+            }
+        }
+        Ok(best)
+    }
+
+    // List pools available for import, reusing a recent scan of the same directory unless `refresh`
+    pub async fn list_importable_pools(&self, dir: Option<&str>, refresh: bool) -> Result<(Vec<ImportablePool>, u64, bool), ZfsError> {
+        if let Some(dir) = dir {
+            if !dir.starts_with('/') {
+                return Err(ZfsError::InvalidArgument(format!("Scan directory '{}' must be absolute", dir)));
+            }
+            validate_device_path(dir)?;
+        }
+
+        // The lock is only held to read or store the cache, never across the scan itself
+        if !refresh {
+            if let Some(scan) = self.importable_cache.lock().unwrap().as_ref() {
+                if scan.dir.as_deref() == dir && scan.taken.elapsed() < IMPORTABLE_CACHE_TTL {
+                    return Ok((scan.pools.clone(), scan.scanned_at, true));
+                }
+            }
+        }
+
+        let mut args = vec!["import"];
+        if let Some(dir) = dir {
+            args.push("-d");
+            args.push(dir);
+        }
+        let args = owned_args(&args);
+        let output = blocking(move || Command::new("zpool").args(&args).output()).await?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() && !stderr.contains("no pools available") {
+            return Err(ZfsError::from_stderr(format!("zpool import failed: {}", stderr.trim())));
+        }
+
+        let mut pools = Vec::new();
+        for line in stdout.lines() {
+            let Some((key, value)) = line.trim().split_once(": ") else { continue };
+            match key {
+                "pool" => pools.push(ImportablePool {
+                    name: value.to_string(),
+                    id: String::new(),
+                    state: String::new(),
+                }),
+                "id" => if let Some(pool) = pools.last_mut() { pool.id = value.to_string() },
+                "state" => if let Some(pool) = pools.last_mut() { pool.state = value.to_string() },
+                _ => {}
+            }
+        }
+
+        let scanned_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        *self.importable_cache.lock().unwrap() = Some(ImportableScan {
+            dir: dir.map(|d| d.to_string()),
+            taken: Instant::now(),
+            scanned_at,
+            pools: pools.clone(),
+        });
+        Ok((pools, scanned_at, false))
+    }
+
+    // Import a pool found by list_importable_pools. A read-only import also skips mounting (-N),
+    // since nothing on the pool can change anyway. Returns the name the pool was imported as.
+    pub async fn import_pool(&self, request: &ImportPoolRequest) -> Result<String, ZfsError> {
+        validate_dataset_name(&request.pool)?;
+        if request.pool.contains('/') {
+            return Err(ZfsError::InvalidArgument(format!("Invalid pool name '{}'", request.pool)));
+        }
+        if let Some(new_name) = &request.new_name {
+            validate_dataset_name(new_name)?;
+            if new_name.contains('/') {
+                return Err(ZfsError::InvalidArgument(format!("Invalid pool name '{}'", new_name)));
+            }
+        }
+        for path in [&request.dir, &request.altroot].into_iter().flatten() {
+            if !path.starts_with('/') {
+                return Err(ZfsError::InvalidArgument(format!("Path '{}' must be absolute", path)));
+            }
+            validate_device_path(path)?;
+        }
+
+        let mut args = vec!["import"];
+        if let Some(dir) = &request.dir {
+            args.extend(["-d", dir.as_str()]);
+        }
+        if let Some(altroot) = &request.altroot {
+            args.extend(["-R", altroot.as_str()]);
+        }
+        if request.readonly {
+            args.extend(["-o", "readonly=on", "-N"]);
+        }
+        args.push(&request.pool);
+        if let Some(new_name) = &request.new_name {
+            args.push(new_name);
+        }
+        run_command("zpool", &args).await?;
+
+        // The imported pool is no longer importable, so drop the cached scan, but first use it to
+        // resolve a numeric id to the pool's name
+        let scanned_name = self
+            .importable_cache
+            .lock()
+            .unwrap()
+            .take()
+            .and_then(|scan| scan.pools.into_iter().find(|pool| pool.id == request.pool))
+            .map(|pool| pool.name);
+        let name = request.new_name.clone().or(scanned_name).unwrap_or_else(|| request.pool.clone());
+        self.invalidate_pool_status(&name);
+        Ok(name)
+    }
+
+    // Every imported pool with its health state
+    pub async fn list_pools(&self) -> Result<Vec<PoolSummary>, ZfsError> {
+        let output = run_command("zpool", &["list", "-H", "-o", "name,health"]).await?;
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(name, health)| PoolSummary {
+                name: name.to_string(),
+                health: health.trim().to_string(),
+            })
+            .collect())
+    }
+
+    // The "status:" explanation zpool status gives for an unhealthy pool, joined onto one line
+    pub async fn get_pool_problem(&self, pool: &str) -> Result<Option<String>, ZfsError> {
+        validate_dataset_name(pool)?;
+        let status = run_command("zpool", &["status", pool]).await?;
+        let mut lines = status.lines().skip_while(|line| !line.trim_start().starts_with("status:"));
+        let Some(first) = lines.next() else { return Ok(None) };
+        let mut problem = first.trim_start().trim_start_matches("status:").trim().to_string();
+        // Continuation lines are indented with a tab; the next section starts with "name:"
+        for line in lines.take_while(|line| line.starts_with('\t')) {
+            problem.push(' ');
+            problem.push_str(line.trim());
+        }
+        Ok(Some(problem))
+    }
+
+    // Cheap presence checks that avoid reading full pool status or dataset properties
+    pub async fn pool_exists(&self, name: &str) -> Result<bool, ZfsError> {
+        validate_dataset_name(name)?;
+        let (zpool, name) = (self.zpool_engine.clone(), name.to_string());
+        Ok(blocking(move || zpool.exists(name)).await?)
+    }
+
+    pub async fn dataset_exists(&self, name: &str) -> Result<bool, ZfsError> {
+        validate_dataset_name(name)?;
+        let name = name.to_string();
+        self.with_engine(move |engine| engine.exists(name)).await
+    }
+
+    // Pool status, served from the cache while younger than the TTL unless `fresh`.
+    // Returns the status, whether it came from the cache and its age in milliseconds.
+    pub async fn get_pool_status(&self, name: &str, fresh: bool) -> Result<(PoolStatus, bool, u64), ZfsError> {
+        validate_dataset_name(name)?;
+        if !fresh {
+            if let Some(entry) = self.status_cache.lock().unwrap().get(name) {
+                let age = entry.taken.elapsed();
+                if age < self.status_cache_ttl {
+                    return Ok((entry.status.clone(), true, age.as_millis() as u64));
+                }
+            }
+        }
+
+        let status = self.read_pool_status(name).await?;
+        if !self.status_cache_ttl.is_zero() {
+            self.status_cache.lock().unwrap().insert(name.to_string(), CachedPoolStatus {
+                taken: Instant::now(),
+                status: status.clone(),
+            });
+        }
+        Ok((status, false, 0))
+    }
+
+    fn invalidate_pool_status(&self, pool: &str) {
+        self.status_cache.lock().unwrap().remove(pool);
+    }
+
+    // Run a zpool subcommand that changes `pool`. The cached status is dropped even when the
+    // command fails, since a failed operation may still have changed the pool.
+    async fn modify_pool(&self, pool: &str, args: &[&str]) -> Result<String, ZfsError> {
+        self.ensure_writable(pool).await?;
+        let result = run_command("zpool", args).await;
+        self.invalidate_pool_status(pool);
+        result
+    }
+
+    // Refuse changes on a pool imported read-only (import_pool with readonly), naming the cause,
+    // instead of letting zfs fail with a bare EROFS. `name` may be a pool, dataset or snapshot.
+    async fn ensure_writable(&self, name: &str) -> Result<(), ZfsError> {
+        let pool = name.split(['/', '@', '#']).next().unwrap_or(name);
+        validate_dataset_name(pool)?;
+        let readonly = run_command("zpool", &["get", "-H", "-o", "value", "readonly", pool]).await?;
+        if readonly.trim() == "on" {
+            return Err(ZfsError::ReadOnly(format!(
+                "Pool '{}' is imported read-only; export it and import it again without readonly to make changes",
+                pool
+            )));
+        }
+        Ok(())
+    }
+
+    // Health plus the full vdev tree; class sections (logs, cache, spares, ...) follow the data vdevs
+    async fn read_pool_status(&self, name: &str) -> Result<PoolStatus, ZfsError> {
+        let status = run_command("zpool", &["status", "-P", "-p", name]).await?;
+
+        let health = status
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("state:"))
+            .map(|state| state.trim().to_string())
+            .unwrap_or_else(|| "UNKNOWN".to_string());
+        let has_checkpoint = status.lines().any(|line| line.trim().starts_with("checkpoint:"));
+
+        let lines: Vec<&str> = status_config_lines(&status).collect();
+        let mut roots = build_vdev_tree(&lines).into_iter();
+        let pool = roots.next().ok_or_else(|| ZfsError::Other(format!("No configuration found for pool '{}'", name)))?;
+        let data_vdevs = pool.children.len() as u32;
+
+        let mut topology = pool.children;
+        topology.extend(roots);
+        Ok((health, data_vdevs, topology, has_checkpoint))
+    }
+
+    // Per-vdev I/O from `zpool iostat -v`. The first row is the pool total; with `interval_ms`
+    // a single sample over that interval is taken (-y drops the since-import row).
+    pub async fn get_pool_iostat(&self, pool: &str, interval_ms: Option<u64>) -> Result<(IostatSample, Vec<IostatSample>), ZfsError> {
+        validate_dataset_name(pool)?;
+        let interval = match interval_ms {
+            Some(ms) if (1..=60_000).contains(&ms) => Some(format!("{}.{:03}", ms / 1000, ms % 1000)),
+            Some(ms) => return Err(ZfsError::InvalidArgument(format!("interval_ms must be between 1 and 60000, got {}", ms))),
+            None => None,
+        };
+        let mut args = vec!["iostat", "-H", "-p", "-v"];
+        if interval.is_some() {
+            args.push("-y");
+        }
+        args.push(pool);
+        if let Some(interval) = &interval {
+            args.push(interval);
+            args.push("1");
+        }
+        let output = run_command("zpool", &args).await?;
+
+        let mut rows = output.lines().filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let [name, alloc, free, read_ops, write_ops, read_bytes, write_bytes] = fields[..] else { return None };
+            Some(IostatSample {
+                name: name.to_string(),
+                alloc: alloc.parse().ok(),
+                free: free.parse().ok(),
+                read_ops: read_ops.parse().ok()?,
+                write_ops: write_ops.parse().ok()?,
+                read_bytes: read_bytes.parse().ok()?,
+                write_bytes: write_bytes.parse().ok()?,
+            })
+        });
+        let total = rows.next().ok_or_else(|| ZfsError::Other(format!("zpool iostat returned no data for pool '{}'", pool)))?;
+        Ok((total, rows.collect()))
+    }
+
+    // Progress of the current or last scan, which is either a scrub or a resilver
+    pub async fn get_scan_status(&self, name: &str) -> Result<ScanStatus, ZfsError> {
+        validate_dataset_name(name)?;
+        let status = run_command("zpool", &["status", name]).await?;
+        Ok(parse_scan_status(&status))
+    }
+
+    // Audit trail of operations on a pool, newest first; a pool with no history yields an empty list
+    pub async fn get_pool_history(&self, pool: &str, internal: bool) -> Result<Vec<PoolHistoryEntry>, ZfsError> {
+        validate_dataset_name(pool)?;
+        let mut args = vec!["history", "-l"];
+        if internal {
+            args.push("-i");
+        }
+        args.push(pool);
+        let output = run_command("zpool", &args).await?;
+
+        // Long format lines look like "2024-01-02.03:04:05 zfs create tank/a [user 0 (root) on host:global]"
+        let mut entries: Vec<PoolHistoryEntry> = output
+            .lines()
+            .filter(|line| !line.starts_with("History for") && !line.trim().is_empty())
+            .filter_map(|line| {
+                let (time, rest) = line.split_once(' ')?;
+                let (command, user) = match rest.rfind(" [user ") {
+                    Some(pos) => {
+                        let details = rest[pos + 2..].trim_end_matches(']');
+                        let user = details
+                            .split_once('(')
+                            .and_then(|(_, after)| after.split_once(')'))
+                            .map(|(name, _)| name.to_string());
+                        (&rest[..pos], user)
+                    }
+                    None => (rest, None),
+                };
+                Some(PoolHistoryEntry { time: time.to_string(), command: command.trim().to_string(), user })
+            })
+            .collect();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    pub async fn get_pool_properties(&self, name: &str) -> Result<PoolProperties, ZfsError> {
+        validate_dataset_name(name)?;
+        let property_list = POOL_PROPERTIES.join(",");
+        let output = run_command("zpool", &["get", "-H", "-p", "-o", "property,value", &property_list, name]).await?;
+
+        let mut properties = PoolProperties::default();
+        for line in output.lines() {
+            let Some((property, value)) = line.split_once('\t') else { continue };
+            let value = value.trim();
+            if value == "-" || value.is_empty() {
+                continue;
+            }
+            match property {
+                "autoexpand" => properties.autoexpand = Some(value == "on"),
+                "autoreplace" => properties.autoreplace = Some(value == "on"),
+                "autotrim" => properties.autotrim = Some(value == "on"),
+                "comment" => properties.comment = Some(value.to_string()),
+                "cachefile" => properties.cachefile = Some(value.to_string()),
+                "failmode" => properties.failmode = Some(value.to_string()),
+                "ashift" => properties.ashift = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Ok(properties)
+    }
+
+    // State of every feature this zfs version knows about, keyed by name without the feature@ prefix
+    pub async fn get_pool_features(&self, pool: &str) -> Result<BTreeMap<String, String>, ZfsError> {
+        validate_dataset_name(pool)?;
+        let output = run_command("zpool", &["get", "-H", "-o", "property,value", "all", pool]).await?;
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(property, value)| {
+                property.strip_prefix("feature@").map(|name| (name.to_string(), value.trim().to_string()))
+            })
+            .collect())
+    }
+
+    // Enable all supported features (within the pool's compatibility set) and report which changed.
+    // This cannot be undone, and older zfs versions may no longer import the pool.
+    pub async fn upgrade_pool(&self, pool: &str) -> Result<Vec<String>, ZfsError> {
+        let before = self.get_pool_features(pool).await?;
+        self.modify_pool(pool, &["upgrade", pool]).await?;
+        let after = self.get_pool_features(pool).await?;
+        Ok(after
+            .into_iter()
+            .filter(|(name, state)| state != "disabled" && before.get(name).map(String::as_str) == Some("disabled"))
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    pub async fn set_pool_property(&self, pool: &str, property: &str, value: &str) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        if !POOL_PROPERTIES.contains(&property) {
+            return Err(ZfsError::InvalidArgument(format!("Invalid pool property '{}'; expected one of: {}", property, POOL_PROPERTIES.join(", "))));
+        }
+        if value.chars().any(|c| c.is_control()) {
+            return Err(ZfsError::InvalidArgument(format!("Invalid value for pool property '{}'", property)));
+        }
+        self.modify_pool(pool, &["set", &format!("{}={}", property, value), pool]).await?;
+        Ok(())
+    }
+
+    // Replace a (typically failed) device and report whether the resilver has started
+    pub async fn replace_vdev(&self, pool: &str, old_device: &str, new_device: &str) -> Result<bool, ZfsError> {
+        validate_dataset_name(pool)?;
+        validate_device_path(old_device)?;
+        validate_device_path(new_device)?;
+
+        self.modify_pool(pool, &["replace", pool, old_device, new_device]).await?;
+
+        let status = run_command("zpool", &["status", pool]).await?;
+        Ok(status.contains("resilver in progress") || status.contains("replacing"))
+    }
+
+    // Attach a device to an existing one, turning it into (or widening) a mirror
+    pub async fn attach_vdev(&self, pool: &str, existing_device: &str, new_device: &str) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        validate_device_path(existing_device)?;
+        validate_device_path(new_device)?;
+        self.modify_pool(pool, &["attach", pool, existing_device, new_device]).await?;
+        Ok(())
+    }
+
+    // Detach a device from a mirror; refuses to drop the last redundant copy
+    pub async fn detach_vdev(&self, pool: &str, device: &str) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        validate_device_path(device)?;
+
+        let status = run_command("zpool", &["status", "-P", pool]).await?;
+        let tree = parse_vdev_tree(&status);
+        let position = tree
+            .iter()
+            .position(|(_, name)| is_same_device(name, device))
+            .ok_or_else(|| ZfsError::NotFound(format!("Device '{}' is not part of pool '{}'", device, pool)))?;
+        let indent = tree[position].0;
+        if let Some(parent) = tree[..position].iter().rposition(|(i, _)| *i < indent) {
+            let parent_indent = tree[parent].0;
+            let siblings = tree[parent + 1..]
+                .iter()
+                .take_while(|(i, _)| *i > parent_indent)
+                .filter(|(i, _)| *i == indent)
+                .count();
+            if tree[parent].1.starts_with("mirror") && siblings <= 2 {
+                return Err(ZfsError::InvalidArgument(format!(
+                    "Detaching '{}' would leave '{}' without redundancy",
+                    device, tree[parent].1
+                )));
+            }
+        }
+
+        self.modify_pool(pool, &["detach", pool, device]).await?;
+        Ok(())
+    }
+
+    // Grow a pool into larger replacement disks: turn on autoexpand, then online every data device
+    // with -e. Returns the size in bytes before and after, and the devices that were expanded; if one
+    // device fails, the error names the ones already expanded, since neither step is rolled back.
+    pub async fn expand_pool(&self, pool: &str) -> Result<(u64, u64, Vec<String>), ZfsError> {
+        validate_dataset_name(pool)?;
+        let old_size: u64 = pool_property(pool, "size").await?.parse()?;
+
+        let (_, data_vdevs, topology, _) = self.read_pool_status(pool).await?;
+        let mut devices = Vec::new();
+        let mut pending: Vec<&VdevInfo> = topology.iter().take(data_vdevs as usize).collect();
+        while let Some(vdev) = pending.pop() {
+            if vdev.children.is_empty() {
+                devices.push(vdev.path.clone().unwrap_or_else(|| vdev.name.clone()));
+            } else {
+                pending.extend(&vdev.children);
+            }
+        }
+        devices.sort();
+
+        self.modify_pool(pool, &["set", "autoexpand=on", pool]).await?;
+        for (index, device) in devices.iter().enumerate() {
+            self.modify_pool(pool, &["online", "-e", pool, device]).await.map_err(|e| {
+                let expanded = if index == 0 { "none".to_string() } else { devices[..index].join(", ") };
+                e.with_context(&format!("autoexpand is now on; devices already expanded: {}", expanded))
+            })?;
+        }
+        Ok((old_size, pool_property(pool, "size").await?.parse()?, devices))
+    }
+
+    // Split one side of every mirror off into `new_pool`, which is left exported. zpool split only
+    // works when every data vdev is a mirror, so check that first and name the ones that are not.
+    pub async fn split_pool(&self, pool: &str, new_pool: &str, devices: Option<&[String]>) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        validate_dataset_name(new_pool)?;
+        if new_pool.contains('/') {
+            return Err(ZfsError::InvalidArgument(format!("'{}' is not a valid pool name", new_pool)));
+        }
+        let devices = devices.unwrap_or_default();
+        for device in devices {
+            validate_device_path(device)?;
+        }
+
+        let (_, data_vdevs, topology, _) = self.read_pool_status(pool).await?;
+        let non_mirrors: Vec<String> = topology
+            .iter()
+            .take(data_vdevs as usize)
+            .filter(|vdev| !vdev.vdev_type.starts_with("mirror"))
+            .map(|vdev| format!("{} ({})", vdev.name, vdev.vdev_type))
+            .collect();
+        if !non_mirrors.is_empty() {
+            return Err(ZfsError::InvalidArgument(format!(
+                "Pool '{}' cannot be split; every data vdev must be a mirror, but these are not: {}",
+                pool,
+                non_mirrors.join(", ")
+            )));
+        }
+
+        let mut args = vec!["split", pool, new_pool];
+        args.extend(devices.iter().map(String::as_str));
+        self.modify_pool(pool, &args).await?;
+        Ok(())
+    }
+
+    // Reset error counters and resume I/O for the whole pool, or for one device after replacing it
+    pub async fn clear_pool(&self, pool: &str, device: Option<&str>) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        let mut args = vec!["clear", pool];
+        if let Some(device) = device {
+            validate_device_path(device)?;
+            args.push(device);
+        }
+        self.modify_pool(pool, &args).await?;
+        Ok(())
+    }
+
+    // Take a device offline for servicing without removing it from the pool
+    pub async fn offline_vdev(&self, pool: &str, device: &str, temporary: bool) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        validate_device_path(device)?;
+        let mut args = vec!["offline"];
+        if temporary {
+            args.push("-t");
+        }
+        args.push(pool);
+        args.push(device);
+        self.modify_pool(pool, &args).await?;
+        Ok(())
+    }
+
+    pub async fn online_vdev(&self, pool: &str, device: &str, expand: bool) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        validate_device_path(device)?;
+        let mut args = vec!["online"];
+        if expand {
+            args.push("-e");
+        }
+        args.push(pool);
+        args.push(device);
+        self.modify_pool(pool, &args).await?;
+        Ok(())
+    }
+
+    // Start a TRIM of the pool's free space; `rate` is bytes per second per device
+    pub async fn start_trim(&self, pool: &str, rate: Option<u64>, secure: bool) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        let rate = rate.map(|r| r.to_string());
+        let mut args = vec!["trim"];
+        if secure {
+            args.push("-d");
+        }
+        if let Some(rate) = &rate {
+            args.push("-r");
+            args.push(rate);
+        }
+        args.push(pool);
+        self.modify_pool(pool, &args).await?;
+        Ok(())
+    }
+
+    pub async fn cancel_trim(&self, pool: &str) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        self.modify_pool(pool, &["trim", "-c", pool]).await?;
+        Ok(())
+    }
+
+    pub async fn trim_status(&self, pool: &str) -> Result<(Vec<VdevProgress>, String, Option<f64>), ZfsError> {
+        validate_dataset_name(pool)?;
+        let status = run_command("zpool", &["status", "-t", pool]).await?;
+        let vdevs = parse_vdev_progress(&status, "trimmed");
+        let (state, percent_done) = summarize_vdev_progress(&vdevs);
+        Ok((vdevs, state, percent_done))
+    }
+
+    // Write to all unallocated space, on every device or only the given one
+    pub async fn start_initialize(&self, pool: &str, device: Option<&str>) -> Result<(), ZfsError> {
+        self.run_initialize(pool, device, false).await
+    }
+
+    pub async fn cancel_initialize(&self, pool: &str, device: Option<&str>) -> Result<(), ZfsError> {
+        self.run_initialize(pool, device, true).await
+    }
+
+    async fn run_initialize(&self, pool: &str, device: Option<&str>, cancel: bool) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        let mut args = vec!["initialize"];
+        if cancel {
+            args.push("-c");
+        }
+        args.push(pool);
+        if let Some(device) = device {
+            validate_device_path(device)?;
+            args.push(device);
+        }
+        self.modify_pool(pool, &args).await?;
+        Ok(())
+    }
+
+    pub async fn initialize_status(&self, pool: &str) -> Result<(Vec<VdevProgress>, String, Option<f64>), ZfsError> {
+        validate_dataset_name(pool)?;
+        let status = run_command("zpool", &["status", "-i", pool]).await?;
+        let vdevs = parse_vdev_progress(&status, "initialized");
+        let (state, percent_done) = summarize_vdev_progress(&vdevs);
+        Ok((vdevs, state, percent_done))
+    }
+
+    // Record the pool's current state so it can later be rewound with zpool import --rewind-to-checkpoint
+    pub async fn checkpoint_pool(&self, pool: &str) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        self.modify_pool(pool, &["checkpoint", pool]).await?;
+        Ok(())
+    }
+
+    pub async fn discard_checkpoint(&self, pool: &str) -> Result<(), ZfsError> {
+        validate_dataset_name(pool)?;
+        self.modify_pool(pool, &["checkpoint", "-d", pool]).await?;
+        Ok(())
+    }
+
+    // Give the pool a new random GUID so a cloned copy of it can be imported alongside the original.
+    // Returns the GUID before and after.
+    pub async fn reguid_pool(&self, pool: &str) -> Result<(String, String), ZfsError> {
+        validate_dataset_name(pool)?;
+        let old_guid = pool_property(pool, "guid").await?;
+        self.modify_pool(pool, &["reguid", pool]).await?;
+        Ok((old_guid, pool_property(pool, "guid").await?))
+    }
+
+    // Read ARC counters from the kernel module's kstat file (Linux only)
+    pub async fn get_arc_stats(&self) -> Result<ArcStats, ZfsError> {
+        let contents = blocking(|| std::fs::read_to_string(ARCSTATS_PATH)).await.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => ZfsError::NotFound(format!(
+                "{} not found; ARC statistics need Linux with the zfs module loaded",
+                ARCSTATS_PATH
+            )),
+            _ => ZfsError::from(e),
+        })?;
+
+        // The first two lines are a kstat header; the rest are "name type value"
+        let stats: HashMap<&str, u64> = contents
+            .lines()
+            .skip(2)
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let name = fields.next()?;
+                let value = fields.nth(1)?.parse().ok()?;
+                Some((name, value))
+            })
+            .collect();
+        let get = |name: &str| stats.get(name).copied().unwrap_or(0);
+
+        let (hits, misses) = (get("hits"), get("misses"));
+        let has_l2 = get("l2_size") > 0;
+        Ok(ArcStats {
+            size: get("size"),
+            c: get("c"),
+            c_min: get("c_min"),
+            c_max: get("c_max"),
+            hits,
+            misses,
+            hit_ratio: (hits + misses > 0).then(|| hits as f64 / (hits + misses) as f64),
+            mru_size: get("mru_size"),
+            mfu_size: get("mfu_size"),
+            mru_hits: get("mru_hits"),
+            mfu_hits: get("mfu_hits"),
+            mru_ghost_hits: get("mru_ghost_hits"),
+            mfu_ghost_hits: get("mfu_ghost_hits"),
+            l2_size: has_l2.then(|| get("l2_size")),
+            l2_hits: has_l2.then(|| get("l2_hits")),
+            l2_misses: has_l2.then(|| get("l2_misses")),
+        })
+    }
+
+    // Clear ZFS labels from a disk that belonged to a destroyed or foreign pool
+    pub async fn labelclear_device(&self, device: &str, force: bool) -> Result<(), ZfsError> {
+        validate_device_path(device)?;
+
+        let mut args = vec!["labelclear"];
+        if force {
+            args.push("-f");
+        }
+        args.push(device);
+
+        run_command("zpool", &args).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn property_value_rejects_control_characters() {
+        assert!(validate_property_value("compression", "lz4\0").is_err());
+        assert!(validate_property_value("com.example:note", "line\nbreak").is_err());
+        assert!(validate_property_value("com.example:note", "line\rbreak").is_err());
+    }
+
+    #[test]
+    fn property_value_rejects_leading_dash() {
+        assert!(validate_property_value("compression", "-o").is_err());
+        assert!(validate_property_value("compression", "lz4-fast").is_ok());
+    }
+
+    #[test]
+    fn property_value_length_cap() {
+        let at_limit = "a".repeat(MAX_PROPERTY_VALUE_LEN);
+        let over_limit = "a".repeat(MAX_PROPERTY_VALUE_LEN + 1);
+        assert!(validate_property_value("com.example:note", &at_limit).is_ok());
+        assert!(validate_property_value("com.example:note", &over_limit).is_err());
+    }
+
+    // Receive input is confined to the send directory; inside it, shell metacharacters are just
+    // part of a literal file name
+    #[tokio::test]
+    async fn receive_input_is_confined_to_send_dir() {
+        let dir = std::env::temp_dir().join(format!("zfs-agent-receive-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let inside = |name: &str| format!("{}/{}", dir.display(), name);
+        std::os::unix::fs::symlink("/", dir.join("escape")).unwrap();
+
+        let literal = open_receive_input(&dir, &inside("'; rm -rf /;")).await;
+        let dotdot = open_receive_input(&dir, &inside("../x")).await;
+        let outside = open_receive_input(&dir, "/etc/hostname").await;
+        let symlink = open_receive_input(&dir, &inside("escape")).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        match literal {
+            Err(ZfsError::NotFound(message)) => assert!(message.contains("does not exist"), "{}", message),
+            other => panic!("expected NotFound, got {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(dotdot, Err(ZfsError::InvalidArgument(_))));
+        assert!(matches!(outside, Err(ZfsError::PermissionDenied(_))));
+        assert!(matches!(symlink, Err(ZfsError::PermissionDenied(_))));
+    }
+
+    #[test]
+    fn diff_lines_parse_one_entry_each() {
+        let renamed = parse_diff_line("R\t/tank/a\t/tank/b").unwrap();
+        assert_eq!((renamed.change_type.as_str(), renamed.path.as_str()), ("renamed", "/tank/a"));
+        assert_eq!(renamed.new_path.as_deref(), Some("/tank/b"));
+        assert_eq!(parse_diff_line("+\t/tank/new").unwrap().change_type, "created");
+        assert!(parse_diff_line("?\t/tank/x").is_none());
+        assert!(parse_diff_line("M").is_none());
+    }
+
+    #[test]
+    fn property_value_equals_only_for_user_properties() {
+        assert!(matches!(
+            validate_property_value("compression", "lz4=on"),
+            Err(ZfsError::InvalidArgument(_))
+        ));
+        assert!(validate_property_value("com.example:note", "key=value").is_ok());
+    }
+
+    // A manager without libzfs_core, for tests of the CLI-backed calls
+    fn cli_only_manager() -> ZfsManager {
+        ZfsManager {
+            engine: None,
+            zpool_engine: Arc::new(ZpoolOpen3::default()),
+            importable_cache: Arc::new(Mutex::new(None)),
+            status_cache: Arc::new(Mutex::new(HashMap::new())),
+            status_cache_ttl: Duration::ZERO,
+            send_defaults: SendDefaults::from_env(),
+            send_dir: std::env::temp_dir(),
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+        }
+    }
+
+    // A fake zpool on PATH hangs on pool "slow" until the test creates the release file. On a
+    // single-threaded runtime, the reads of pool "fast" can only finish before that if the hung call
+    // is parked on the blocking pool rather than on the runtime's only worker.
+    #[tokio::test(flavor = "current_thread")]
+    async fn status_reads_run_while_a_slow_zpool_call_is_in_flight() {
+        let dir = std::env::temp_dir().join(format!("zfs-agent-status-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (started, release) = (dir.join("started"), dir.join("release"));
+        let script = format!(
+            r#"#!/bin/sh
+for pool; do :; done
+if [ "$pool" = slow ]; then
+  touch '{started}'
+  i=0; while [ ! -e '{release}' ]; do i=$((i+1)); [ $i -gt 1000 ] && exit 1; sleep 0.01; done
+fi
+printf '  pool: %s\n state: ONLINE\nconfig:\n\n\tNAME STATE READ WRITE CKSUM\n\t%s ONLINE 0 0 0\n\t  /dev/sda ONLINE 0 0 0\n\nerrors: No known data errors\n' "$pool" "$pool"
+"#,
+            started = started.display(),
+            release = release.display(),
+        );
+        let zpool = dir.join("zpool");
+        std::fs::write(&zpool, script).unwrap();
+        std::fs::set_permissions(&zpool, std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+        std::env::set_var("PATH", format!("{}:{}", dir.display(), std::env::var("PATH").unwrap_or_default()));
+
+        let manager = cli_only_manager();
+        let slow = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.get_scan_status("slow").await }
+        });
+        while !started.exists() && !slow.is_finished() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut reads = Vec::new();
+        for _ in 0..4 {
+            reads.push(manager.get_pool_status("fast", true).await);
+        }
+        let slow_pending = !slow.is_finished();
+        std::fs::write(&release, "").unwrap();
+        let slow = slow.await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(slow_pending, "slow call finished before it was released");
+        for read in reads {
+            let ((health, data_vdevs, _, _), cached, _) = read.unwrap();
+            assert_eq!((health.as_str(), data_vdevs, cached), ("ONLINE", 1, false));
+        }
+        assert!(slow.is_ok(), "{:?}", slow.err());
+    }
+}